@@ -0,0 +1,163 @@
+// static looks at a ROM without running it: which opcodes it uses
+// and what it disassembles to, for the --analyze and --disasm modes.
+// Everything is read two bytes at a time from the start of the ROM,
+// so data mixed in with the code shows up as whatever it happens to decode to
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use rite_emu::instruction::{decode, pattern_for, Instruction};
+use serde_json::{json, Value};
+
+use crate::rominfo::RomInfo;
+
+/// where ROMs get loaded, so disassembly addresses line up with the debugger's
+const PROGRAM_START: u16 = 0x200;
+
+/// One two byte word of a ROM.
+///
+/// # Fields
+/// * `address` - where it ends up in memory
+/// * `opcode` - the word itself
+/// * `instruction` - what it decodes to, `None` if it isn't an instruction
+pub struct Line {
+    pub address: u16,
+    pub opcode: u16,
+    pub instruction: Option<Instruction>,
+}
+
+/// Everything the analysis modes report about a ROM.
+///
+/// # Fields
+/// * `info` - size and hashes, same as `--info`
+/// * `histogram` - how many times each opcode pattern shows up
+/// * `unknown` - words that aren't any instruction
+/// * `disassembly` - every word of the ROM, decoded
+pub struct Analysis {
+    pub info: RomInfo,
+    pub histogram: BTreeMap<&'static str, usize>,
+    pub unknown: usize,
+    pub disassembly: Vec<Line>,
+}
+
+impl Analysis {
+    pub fn new(rom: &[u8]) -> Self {
+        let disassembly: Vec<Line> = rom
+            .chunks_exact(2)
+            .zip((PROGRAM_START..).step_by(2))
+            .map(|(bytes, address)| {
+                let opcode = u16::from_be_bytes([bytes[0], bytes[1]]);
+                Line {
+                    address,
+                    opcode,
+                    instruction: decode(opcode).ok(),
+                }
+            })
+            .collect();
+
+        let mut histogram = BTreeMap::new();
+        let mut unknown = 0;
+        for line in &disassembly {
+            match line.instruction.and(pattern_for(line.opcode)) {
+                Some(pattern) => *histogram.entry(pattern).or_insert(0) += 1,
+                None => unknown += 1,
+            }
+        }
+
+        Analysis {
+            info: RomInfo::new(rom),
+            histogram,
+            unknown,
+            disassembly,
+        }
+    }
+
+    /// `--info`, for printing
+    pub fn info_text(&self) -> String {
+        self.info.to_string()
+    }
+
+    /// `--analyze`, for printing: the info, then each opcode pattern with how often it's used
+    pub fn analysis_text(&self) -> String {
+        let mut text = self.info_text();
+        for (pattern, count) in &self.histogram {
+            write!(text, "\n{}  {}", pattern, count).unwrap();
+        }
+        write!(text, "\nunknown  {}", self.unknown).unwrap();
+        text
+    }
+
+    /// `--disasm`, for printing, laid out like the debugger's `disasm`
+    pub fn disassembly_text(&self) -> String {
+        self.disassembly
+            .iter()
+            .map(|line| match line.instruction {
+                Some(instruction) => {
+                    format!(
+                        "0x{:03X}  {:04X}  {}",
+                        line.address, line.opcode, instruction
+                    )
+                }
+                None => format!("0x{:03X}  {:04X}  ???", line.address, line.opcode),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// `--info --json`
+    pub fn info_json(&self) -> Value {
+        json!({
+            "size": self.info.size,
+            "sha1": self.info.sha1_hex(),
+            "crc32": format!("{:08x}", self.info.crc32),
+        })
+    }
+
+    /// `--analyze --json`
+    pub fn analysis_json(&self) -> Value {
+        json!({
+            "info": self.info_json(),
+            "opcodes": self.histogram,
+            "unknown": self.unknown,
+        })
+    }
+
+    /// `--disasm --json`, with `null` for words that aren't instructions
+    pub fn disassembly_json(&self) -> Value {
+        self.disassembly
+            .iter()
+            .map(|line| {
+                json!({
+                    "address": line.address,
+                    "opcode": format!("{:04X}", line.opcode),
+                    "instruction": line.instruction.map(|instruction| instruction.to_string()),
+                })
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_analysis_json() {
+    // LD V0, 0x10; LD V1, 0x20; DRW V0, V1, 5; not an instruction; and one byte left over
+    let rom = [0x60, 0x10, 0x61, 0x20, 0xd0, 0x15, 0x8a, 0xb9, 0x12];
+    let analysis = Analysis::new(&rom);
+
+    let report = analysis.analysis_json();
+    assert_eq!(report["info"]["size"], 9);
+    assert_eq!(report["info"]["sha1"].as_str().unwrap().len(), 40);
+    assert_eq!(report["opcodes"], json!({"6XNN": 2, "DXYN": 1}));
+    assert_eq!(report["unknown"], 1);
+
+    let disassembly = analysis.disassembly_json();
+    assert_eq!(disassembly.as_array().unwrap().len(), 4);
+    assert_eq!(
+        disassembly[0],
+        json!({"address": 0x200, "opcode": "6010", "instruction": "LD V0, 0x10"})
+    );
+    assert_eq!(disassembly[3]["instruction"], Value::Null);
+    assert_eq!(
+        analysis.disassembly_text().lines().nth(2),
+        Some("0x204  D015  DRW V0, V1, 5")
+    );
+}
@@ -0,0 +1,90 @@
+// the beep that plays while the sound timer is above zero.
+// audio needs the ggez `Context`, so this lives in the frontend
+// and just follows `Emu::is_beeping`
+
+use ggez::audio::{SoundData, SoundSource, Source};
+use ggez::Context;
+
+// 44 kHz divides evenly by 440 Hz, so the tone loops without a click
+const SAMPLE_RATE: u32 = 44000;
+const FREQUENCY: u32 = 440;
+const VOLUME: i16 = i16::MAX / 8; // square waves are loud
+
+/// An endlessly looping square wave that gets paused and resumed.
+/// If there's no audio to be had, this just stays quiet.
+pub struct Beeper {
+    source: Option<Source>,
+}
+
+impl Beeper {
+    pub fn new(ctx: &mut Context) -> Self {
+        let data = SoundData::from_bytes(&square_wave_wav(SAMPLE_RATE / 10));
+        // no audio device (or anything else going wrong) just means no beeping
+        let source = Source::from_data(ctx, data).ok().and_then(|mut source| {
+            source.set_repeat(true);
+            source.play(ctx).ok()?;
+            source.pause();
+            Some(source)
+        });
+        Beeper { source }
+    }
+
+    /// starts or stops the tone to match `beeping`
+    pub fn update(&mut self, beeping: bool) {
+        if let Some(source) = &self.source {
+            if beeping && source.paused() {
+                source.resume();
+            } else if !beeping && !source.paused() {
+                source.pause();
+            }
+        }
+    }
+}
+
+/// builds a 16-bit mono WAV file holding `samples` samples of square wave
+fn square_wave_wav(samples: u32) -> Vec<u8> {
+    let data_size = samples * 2;
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+
+    // RIFF header
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    // format chunk: PCM, 1 channel, sample rate, byte rate, block align, bits per sample
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    // the samples themselves
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    let half_period = SAMPLE_RATE / FREQUENCY / 2;
+    for sample in 0..samples {
+        let level = if (sample / half_period).is_multiple_of(2) {
+            VOLUME
+        } else {
+            -VOLUME
+        };
+        wav.extend_from_slice(&level.to_le_bytes());
+    }
+
+    wav
+}
+
+#[test]
+fn test_square_wave_wav() {
+    let wav = square_wave_wav(200);
+    assert_eq!(wav.len(), 44 + 400);
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[36..40], b"data");
+    // 50 samples high, then 50 samples low
+    let sample = |n: usize| i16::from_le_bytes([wav[44 + n * 2], wav[45 + n * 2]]);
+    assert_eq!(sample(0), VOLUME);
+    assert_eq!(sample(49), VOLUME);
+    assert_eq!(sample(50), -VOLUME);
+    assert_eq!(sample(100), VOLUME);
+}
@@ -0,0 +1,180 @@
+// capturing frames to numbered PNGs,
+// so they can be stitched together into a GIF afterwards,
+// and dumping the last frame when the emulator exits
+
+use std::fs::{create_dir_all, write, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use rite_emu::emu::Emu;
+
+/// how many times larger than the CHIP-8 pixels the captured images are
+const CAPTURE_SCALE: usize = 10;
+
+/// Writes the screen out as one PNG per captured frame.
+///
+/// # Fields
+/// * `dir` - directory the frames are written to
+/// * `next_frame` - number of the next frame to be written
+/// * `pending` - frames requested with a keypress that haven't run yet
+/// * `every` - automatically advance after this many ticks, `None` to only advance on keypresses
+/// * `ticks` - ticks since the last automatic advance
+pub struct Capture {
+    dir: PathBuf,
+    next_frame: u32,
+    pending: u32,
+    every: Option<u32>,
+    ticks: u32,
+}
+
+impl Capture {
+    /// sets up capturing into `dir`, creating it if it doesn't exist yet
+    pub fn new(dir: &Path, every: Option<u32>) -> io::Result<Self> {
+        create_dir_all(dir)?;
+        Ok(Capture {
+            dir: dir.to_path_buf(),
+            next_frame: 0,
+            pending: 0,
+            every,
+            ticks: 0,
+        })
+    }
+
+    /// asks for one more frame to be run and captured
+    pub fn request_frame(&mut self) {
+        self.pending += 1;
+    }
+
+    /// called once per tick, returns true if a frame should be run this tick
+    pub fn should_advance(&mut self) -> bool {
+        if self.pending > 0 {
+            self.pending -= 1;
+            return true;
+        }
+        if let Some(every) = self.every {
+            self.ticks += 1;
+            if self.ticks >= every {
+                self.ticks = 0;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// path of the next frame, zero padded so they sort in order
+    fn frame_path(&self) -> PathBuf {
+        self.dir.join(format!("frame_{:06}.png", self.next_frame))
+    }
+
+    /// writes `pixels` out as the next frame, returning where it was written
+    pub fn save_frame(
+        &mut self,
+        pixels: &[bool],
+        width: usize,
+        height: usize,
+    ) -> io::Result<PathBuf> {
+        let path = self.frame_path();
+        write_png(&path, pixels, width, height)?;
+        self.next_frame += 1;
+        Ok(path)
+    }
+}
+
+/// writes a black and white PNG of `pixels`, scaled up by `CAPTURE_SCALE`
+pub fn write_png(path: &Path, pixels: &[bool], width: usize, height: usize) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(
+        file,
+        (width * CAPTURE_SCALE) as u32,
+        (height * CAPTURE_SCALE) as u32,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut data = Vec::with_capacity(width * height * CAPTURE_SCALE * CAPTURE_SCALE);
+    for row in pixels.chunks(width) {
+        for _ in 0..CAPTURE_SCALE {
+            for pixel in row {
+                let shade = if *pixel { 0xff } else { 0x00 };
+                data.extend(std::iter::repeat_n(shade, CAPTURE_SCALE));
+            }
+        }
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+/// writes the screen to `path`, as a PNG if that's its extension,
+/// and otherwise as the raw one bit per pixel layout from `Emu::framebuffer_packed`
+#[allow(deprecated)]
+pub fn dump_screen(path: &Path, emulator: &Emu) -> io::Result<()> {
+    if path.extension().is_some_and(|extension| extension == "png") {
+        write_png(
+            path,
+            emulator.pixels(),
+            emulator.screen_width(),
+            emulator.screen_height(),
+        )
+    } else {
+        write(path, emulator.framebuffer_packed())
+    }
+}
+
+#[test]
+fn test_capture_frame_numbering() {
+    let dir = std::env::temp_dir().join(format!("rite-emu-capture-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // the directory doesn't exist and should get created
+    let mut capture = Capture::new(&dir.join("nested"), None).unwrap();
+    let pixels = vec![false; 64 * 32];
+    let first = capture.save_frame(&pixels, 64, 32).unwrap();
+    let second = capture.save_frame(&pixels, 64, 32).unwrap();
+
+    assert_eq!(first.file_name().unwrap(), "frame_000000.png");
+    assert_eq!(second.file_name().unwrap(), "frame_000001.png");
+    assert!(second.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_capture_advancing() {
+    let dir = std::env::temp_dir().join(format!("rite-emu-advance-{}", std::process::id()));
+    let mut capture = Capture::new(&dir, Some(3)).unwrap();
+
+    // keypresses are used up first, then every third tick advances
+    capture.request_frame();
+    let advanced: Vec<bool> = (0..7).map(|_| capture.should_advance()).collect();
+    assert_eq!(advanced, [true, false, false, true, false, false, true]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_dump_screen() {
+    let dir = std::env::temp_dir().join(format!("rite-emu-dump-{}", std::process::id()));
+    create_dir_all(&dir).unwrap();
+
+    // LD I, font for 0; DRW V0, V0, 5
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xa0, 0x50, 0xd0, 0x05]).unwrap();
+    emulator.step_frame(2).unwrap();
+
+    let raw = dir.join("screen.bin");
+    dump_screen(&raw, &emulator).unwrap();
+    let bytes = std::fs::read(&raw).unwrap();
+    assert_eq!(bytes, emulator.framebuffer_packed());
+    assert_eq!(bytes[0], 0xf0);
+
+    let png = dir.join("screen.png");
+    dump_screen(&png, &emulator).unwrap();
+    let decoder = png::Decoder::new(File::open(&png).unwrap());
+    let reader = decoder.read_info().unwrap();
+    assert_eq!((reader.info().width, reader.info().height), (640, 320));
+
+    assert!(dump_screen(&dir.join("missing").join("screen.png"), &emulator).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
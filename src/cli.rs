@@ -0,0 +1,309 @@
+// command line handling for the frontend.
+// nothing fancy here, we just walk the arguments by hand
+// so we don't have to pull in a whole argument parsing crate
+
+use std::path::PathBuf;
+
+use crate::keymap::Keymap;
+use crate::recovery::ErrorPolicy;
+use rite_emu::emu::{DrawMode, Platform, Quirks, WatchTarget, ZeroOpcodeBehavior};
+use rite_emu::instruction::supported_opcodes;
+use rite_emu::renderer::{Orientation, Rotation};
+
+/// most frames one update will run to catch up after falling behind
+pub const DEFAULT_MAX_CATCH_UP: u32 = 5;
+
+/// Represents everything the user can configure from the command line.
+///
+/// # Fields
+/// * `rom_path` - path to the ROM, `-` to read the ROM itself from stdin, if not given we ask for it on stdin
+/// * `sticky_keys` - keypresses toggle a key instead of needing it held down
+/// * `sticky_release` - frames until a sticky key lets go by itself, `None` if never
+/// * `key_repeat` - frames before a held key repeats and frames between repeats, `None` to just stay down
+/// * `quirks` - which interpreter's behavior to follow, `None` to leave it to the ROM's config
+/// * `zero_opcode_behavior` - what the emulator does when it hits a `0000`, `None` to leave it to the quirks
+/// * `cycles_per_frame` - instructions to run every frame, `None` to leave it to the ROM's config
+/// * `adaptive_cycles` - instructions a frame didn't get to run roll over into the next one
+/// * `info` - print the ROM's size and hashes instead of running it
+/// * `analyze` - print the info plus how often each opcode shows up instead of running it
+/// * `disasm` - print the whole ROM disassembled instead of running it
+/// * `listing` - print the whole ROM disassembled with a comment on each instruction instead of running it
+/// * `json` - print the reports above as JSON instead of text
+/// * `dump_screen` - file to write the last frame to on exit, `None` to not
+/// * `capture_dir` - directory to capture frames into, `None` if not capturing
+/// * `capture_every` - ticks between captured frames, `None` to only step on keypresses
+/// * `watches` - registers and memory to print changes to
+/// * `watch` - reload the ROM and start it over whenever the file changes
+/// * `seed` - seed for the random number generator, `None` to pick one
+/// * `smoke` - seconds to run the ROM without a window before printing how it went, `None` to run it normally
+/// * `slow` - milliseconds between instructions for watching a ROM run, `None` to run at full speed
+/// * `max_catch_up` - most frames to run at once after falling behind, `None` for no limit
+/// * `max_instructions_per_update` - most instructions to run at once after falling behind, `None` for no limit
+/// * `repl` - run the ROM from a command line debugger instead of a window
+/// * `xo_chip` - give the machine 64 kB of memory like XO-CHIP
+/// * `on_error` - what to do when the ROM runs into an emulation error
+/// * `detect_self_modifying` - print a line whenever the ROM writes into code that's about to run
+/// * `log_pixels` - print a line for every pixel drawing turns on or off
+/// * `lint` - warn about things the ROM probably didn't mean to do
+/// * `draw_mode` - how sprites get drawn, `DrawMode::Or` for looking at sprite data
+/// * `denied` - opcode patterns the ROM isn't allowed to run
+/// * `platform` - fail on instructions from outside this platform, `None` to allow everything
+/// * `scanlines` - draw faint scanlines over the screen like an old CRT
+/// * `orientation` - which way up to show the screen, for displays mounted rotated or mirrored
+/// * `blank_screen_hint` - frames without drawing anything before suggesting what might be wrong, `None` to never
+/// * `layout` - which machine's keypad to lay out on the keyboard, `None` for the COSMAC VIP
+pub struct Options {
+    pub rom_path: Option<String>,
+    pub sticky_keys: bool,
+    pub sticky_release: Option<u32>,
+    pub key_repeat: Option<(u32, u32)>,
+    pub quirks: Option<Quirks>,
+    pub zero_opcode_behavior: Option<ZeroOpcodeBehavior>,
+    pub cycles_per_frame: Option<u32>,
+    pub adaptive_cycles: bool,
+    pub info: bool,
+    pub analyze: bool,
+    pub disasm: bool,
+    pub listing: bool,
+    pub json: bool,
+    pub dump_screen: Option<PathBuf>,
+    pub capture_dir: Option<PathBuf>,
+    pub capture_every: Option<u32>,
+    pub watches: Vec<WatchTarget>,
+    pub watch: bool,
+    pub seed: Option<u64>,
+    pub slow: Option<u64>,
+    pub smoke: Option<u32>,
+    pub max_catch_up: Option<u32>,
+    pub max_instructions_per_update: Option<u32>,
+    pub repl: bool,
+    pub xo_chip: bool,
+    pub on_error: ErrorPolicy,
+    pub detect_self_modifying: bool,
+    pub log_pixels: bool,
+    pub lint: bool,
+    pub draw_mode: DrawMode,
+    pub denied: Vec<String>,
+    pub platform: Option<Platform>,
+    pub scanlines: bool,
+    pub orientation: Orientation,
+    pub blank_screen_hint: Option<u64>,
+    pub layout: Option<Keymap>,
+}
+
+impl Options {
+    /// builds `Options` from the arguments given to the program,
+    /// not including the program name itself.
+    /// Returns a message describing the problem if something isn't understood.
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options {
+            rom_path: None,
+            sticky_keys: false,
+            sticky_release: None,
+            key_repeat: None,
+            quirks: None,
+            zero_opcode_behavior: None,
+            cycles_per_frame: None,
+            adaptive_cycles: false,
+            info: false,
+            analyze: false,
+            disasm: false,
+            listing: false,
+            json: false,
+            dump_screen: None,
+            capture_dir: None,
+            capture_every: None,
+            watches: vec![],
+            watch: false,
+            seed: None,
+            slow: None,
+            smoke: None,
+            max_catch_up: Some(DEFAULT_MAX_CATCH_UP),
+            max_instructions_per_update: None,
+            repl: false,
+            xo_chip: false,
+            on_error: ErrorPolicy::default(),
+            detect_self_modifying: false,
+            log_pixels: false,
+            lint: false,
+            draw_mode: DrawMode::default(),
+            denied: vec![],
+            platform: None,
+            scanlines: false,
+            orientation: Orientation::default(),
+            blank_screen_hint: None,
+            layout: None,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--adaptive-cycles" => options.adaptive_cycles = true,
+                "--analyze" => options.analyze = true,
+                "--blank-screen-hint" => {
+                    options.blank_screen_hint = Some(Self::value_for(&arg, args.next())?);
+                }
+                "--capture" => {
+                    let dir: String = Self::value_for(&arg, args.next())?;
+                    options.capture_dir = Some(PathBuf::from(dir));
+                }
+                "--capture-every" => {
+                    options.capture_every = Some(Self::value_for(&arg, args.next())?);
+                }
+                "--cycles-per-frame" => {
+                    options.cycles_per_frame = Some(Self::value_for(&arg, args.next())?);
+                }
+                "--deny" => {
+                    let pattern: String = Self::value_for(&arg, args.next())?;
+                    if !supported_opcodes()
+                        .iter()
+                        .any(|info| info.pattern.eq_ignore_ascii_case(&pattern))
+                    {
+                        return Err(format!("{} can't use the value {}", arg, pattern));
+                    }
+                    options.denied.push(pattern);
+                }
+                "--detect-self-modifying" => options.detect_self_modifying = true,
+                "--disasm" => options.disasm = true,
+                "--draw-mode" => {
+                    let value: String = Self::value_for(&arg, args.next())?;
+                    options.draw_mode = match value.as_str() {
+                        "xor" => DrawMode::Xor,
+                        "or" => DrawMode::Or,
+                        _ => return Err(format!("{} can't use the value {}", arg, value)),
+                    };
+                }
+                "--dump-screen" => {
+                    let path: String = Self::value_for(&arg, args.next())?;
+                    options.dump_screen = Some(PathBuf::from(path));
+                }
+                "--flip-h" => options.orientation.flip_h = true,
+                "--flip-v" => options.orientation.flip_v = true,
+                "--info" => options.info = true,
+                "--json" => options.json = true,
+                "--key-repeat" => {
+                    let value: String = Self::value_for(&arg, args.next())?;
+                    let repeat = value
+                        .split_once(',')
+                        .and_then(|(delay, rate)| Some((delay.parse().ok()?, rate.parse().ok()?)))
+                        .filter(|(_, rate)| *rate > 0)
+                        .ok_or(format!("{} can't use the value {}", arg, value))?;
+                    options.key_repeat = Some(repeat);
+                }
+                "--layout" => {
+                    let name: String = Self::value_for(&arg, args.next())?;
+                    let layout = Keymap::layout(&name)
+                        .ok_or(format!("{} can't use the value {}", arg, name))?;
+                    options.layout = Some(layout);
+                }
+                "--lint" => options.lint = true,
+                "--listing" => options.listing = true,
+                "--log-pixels" => options.log_pixels = true,
+                "--max-catch-up" => {
+                    // 0 turns the limit off
+                    let frames = Self::value_for(&arg, args.next())?;
+                    options.max_catch_up = Some(frames).filter(|frames| *frames > 0);
+                }
+                "--max-instructions-per-update" => {
+                    options.max_instructions_per_update = Some(Self::value_for(&arg, args.next())?);
+                }
+                "--on-error" => {
+                    let value: String = Self::value_for(&arg, args.next())?;
+                    options.on_error = match value.as_str() {
+                        "quit" => ErrorPolicy::Quit,
+                        "pause" => ErrorPolicy::Pause,
+                        "reset" => ErrorPolicy::Reset,
+                        _ => return Err(format!("{} can't use the value {}", arg, value)),
+                    };
+                }
+                "--platform" => {
+                    let value: String = Self::value_for(&arg, args.next())?;
+                    options.platform = Some(match value.as_str() {
+                        "chip8" => Platform::Chip8,
+                        "schip" => Platform::Schip,
+                        "xochip" => Platform::XoChip,
+                        _ => return Err(format!("{} can't use the value {}", arg, value)),
+                    });
+                }
+                "--quirks" => {
+                    let name: String = Self::value_for(&arg, args.next())?;
+                    let quirks = Quirks::preset(&name)
+                        .ok_or(format!("{} can't use the value {}", arg, name))?;
+                    options.quirks = Some(quirks);
+                }
+                "--repl" => options.repl = true,
+                "--rotate" => {
+                    let degrees: String = Self::value_for(&arg, args.next())?;
+                    options.orientation.rotation = match degrees.as_str() {
+                        "0" => Rotation::Rotate0,
+                        "90" => Rotation::Rotate90,
+                        "180" => Rotation::Rotate180,
+                        "270" => Rotation::Rotate270,
+                        _ => return Err(format!("{} can't use the value {}", arg, degrees)),
+                    };
+                }
+                "--scanlines" => options.scanlines = true,
+                "--seed" => options.seed = Some(Self::value_for(&arg, args.next())?),
+                "--slow" => {
+                    let ms = Self::value_for(&arg, args.next())?;
+                    if ms == 0 {
+                        return Err(format!("{} can't use the value {}", arg, ms));
+                    }
+                    options.slow = Some(ms);
+                }
+                "--smoke" => options.smoke = Some(Self::value_for(&arg, args.next())?),
+                "--stdin" => options.rom_path = Some("-".to_string()),
+                "--sticky-keys" => options.sticky_keys = true,
+                "--sticky-release" => {
+                    let frames = Self::value_for(&arg, args.next())?;
+                    options.sticky_keys = true;
+                    options.sticky_release = Some(frames);
+                }
+                "--zero-opcode" => {
+                    let value: String = Self::value_for(&arg, args.next())?;
+                    options.zero_opcode_behavior = Some(match value.as_str() {
+                        "error" => ZeroOpcodeBehavior::Error,
+                        "nop" => ZeroOpcodeBehavior::Nop,
+                        "halt" => ZeroOpcodeBehavior::Halt,
+                        _ => return Err(format!("{} can't use the value {}", arg, value)),
+                    });
+                }
+                "--watch" => options.watch = true,
+                "--watch-register" => {
+                    let register: String = Self::value_for(&arg, args.next())?;
+                    let register = parse_number(&register)
+                        .filter(|register| *register <= 0xf)
+                        .ok_or(format!("{} can't use the value {}", arg, register))?;
+                    options.watches.push(WatchTarget::Register(register as u8));
+                }
+                "--watch-memory" => {
+                    let address: String = Self::value_for(&arg, args.next())?;
+                    let address = parse_number(&address)
+                        .ok_or(format!("{} can't use the value {}", arg, address))?;
+                    options.watches.push(WatchTarget::Memory(address));
+                }
+                "--xo-chip" => options.xo_chip = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ => options.rom_path = Some(arg),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// parses the value following a flag like `--sticky-release 30`
+    fn value_for<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+        let value = value.ok_or(format!("{} expects a value", flag))?;
+        value
+            .parse()
+            .map_err(|_| format!("{} can't use the value {}", flag, value))
+    }
+}
+
+/// parses a number that may be written in hex with a leading `0x`,
+/// since that's how addresses and registers usually get written
+pub fn parse_number(text: &str) -> Option<u16> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -0,0 +1,239 @@
+// pacing for frontends that don't have ggez's `check_update_time`.
+// A `Clock` turns elapsed time into a number of instructions to run,
+// carrying over the leftover fraction so nothing drifts over time.
+
+use std::time::{Duration, Instant};
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// Keeps track of how many instructions are owed for a target speed.
+///
+/// # Fields
+/// * `instructions` - how many instructions run every `nanos` nanoseconds
+/// * `nanos` - the length of time `instructions` is given for
+/// * `owed` - leftover time from earlier ticks, in nanoseconds times `instructions`
+/// * `last_tick` - when `tick` was last called
+pub struct Clock {
+    instructions: u128,
+    nanos: u128,
+    owed: u128,
+    last_tick: Instant,
+}
+
+impl Clock {
+    pub fn new(instructions_per_second: u32) -> Self {
+        Clock {
+            instructions: instructions_per_second as u128,
+            nanos: NANOS_PER_SECOND,
+            owed: 0,
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// a clock for one instruction every `period`, for speeds too slow to count per second
+    pub fn every(period: Duration) -> Self {
+        Clock {
+            instructions: 1,
+            nanos: period.as_nanos().max(1),
+            owed: 0,
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// returns how many instructions should run for the time since the last tick
+    pub fn tick(&mut self) -> usize {
+        let now = Instant::now();
+        let elapsed = now - self.last_tick;
+        self.last_tick = now;
+        self.advance(elapsed)
+    }
+
+    /// returns how many instructions should run for `elapsed` worth of time.
+    /// Whatever doesn't add up to a full instruction is kept for next time.
+    pub fn advance(&mut self, elapsed: Duration) -> usize {
+        self.owed += elapsed.as_nanos() * self.instructions;
+        let instructions = self.owed / self.nanos;
+        self.owed %= self.nanos;
+        instructions as usize
+    }
+}
+
+/// Turns elapsed time into a number of fixed length frames to run,
+/// like ggez's `check_update_time`, but refusing to ask for more than
+/// `max_frames` at once. After a long hitch (a slow frame, the window being dragged, ...)
+/// the extra time is dropped instead of being caught up on forever.
+///
+/// # Fields
+/// * `frame_length` - how long one frame is
+/// * `residual` - time that hasn't added up to a whole frame yet
+/// * `max_frames` - most frames that one call will ask for, `None` for no limit
+pub struct FrameTimer {
+    frame_length: Duration,
+    residual: Duration,
+    max_frames: Option<u32>,
+}
+
+impl FrameTimer {
+    pub fn new(frames_per_second: u32, max_frames: Option<u32>) -> Self {
+        FrameTimer {
+            frame_length: Duration::from_secs(1) / frames_per_second,
+            residual: Duration::ZERO,
+            max_frames,
+        }
+    }
+
+    /// returns how many frames should run for `elapsed` worth of time
+    pub fn frames(&mut self, elapsed: Duration) -> u32 {
+        self.residual += elapsed;
+        let mut frames = (self.residual.as_nanos() / self.frame_length.as_nanos()) as u32;
+        if let Some(max_frames) = self.max_frames {
+            frames = frames.min(max_frames);
+        }
+        self.residual -= self.frame_length * frames;
+        if self.max_frames == Some(frames) {
+            // whole frames we didn't get to are dropped for good
+            self.residual = Duration::from_nanos(
+                (self.residual.as_nanos() % self.frame_length.as_nanos()) as u64,
+            );
+        }
+        frames
+    }
+}
+
+/// the most frames of `per_frame` instructions that fit in `max_instructions`,
+/// for capping catch-up by how much work it is rather than how many frames.
+/// Never less than one, so the ROM always gets to run
+pub fn frames_within(max_instructions: u32, per_frame: u32) -> u32 {
+    (max_instructions / per_frame.max(1)).max(1)
+}
+
+/// How many instructions each frame gets to run.
+/// Frames that stop early (say on a `DXYN` with the display-wait quirk)
+/// can hand their leftovers on to the next frame when `adaptive` is on,
+/// so the ROM still averages the speed it's meant to run at.
+///
+/// # Fields
+/// * `per_frame` - instructions a frame gets on its own
+/// * `adaptive` - whether leftovers roll over into the next frame
+/// * `carried` - leftovers from the last frame, never more than `per_frame`
+///   so a ROM waiting on a key doesn't bank up a huge burst
+pub struct InstructionBudget {
+    per_frame: u32,
+    adaptive: bool,
+    carried: u32,
+}
+
+impl InstructionBudget {
+    pub fn new(per_frame: u32, adaptive: bool) -> Self {
+        InstructionBudget {
+            per_frame,
+            adaptive,
+            carried: 0,
+        }
+    }
+
+    /// how many instructions the next frame may run
+    pub fn next_frame(&self) -> u32 {
+        self.per_frame + self.carried
+    }
+
+    /// records that the last frame ran `ran` of the instructions it was given
+    pub fn spend(&mut self, ran: u32) {
+        self.carried = if self.adaptive {
+            self.next_frame().saturating_sub(ran).min(self.per_frame)
+        } else {
+            0
+        };
+    }
+}
+
+#[test]
+fn test_clock_advance() {
+    let mut clock = Clock::new(700);
+    assert_eq!(clock.advance(Duration::from_secs(1)), 700);
+    assert_eq!(clock.advance(Duration::ZERO), 0);
+
+    // 700 per second is one per 1/700th of a second,
+    // so a millisecond isn't enough for one, but two are
+    assert_eq!(clock.advance(Duration::from_millis(1)), 0);
+    assert_eq!(clock.advance(Duration::from_millis(1)), 1);
+
+    // leftovers add up instead of getting lost
+    let mut clock = Clock::new(60);
+    let total: usize = (0..1000)
+        .map(|_| clock.advance(Duration::from_millis(1)))
+        .sum();
+    assert_eq!(total, 60);
+}
+
+#[test]
+fn test_clock_every() {
+    // one every half second
+    let mut clock = Clock::every(Duration::from_millis(500));
+    assert_eq!(clock.advance(Duration::from_millis(400)), 0);
+    assert_eq!(clock.advance(Duration::from_millis(100)), 1);
+    assert_eq!(clock.advance(Duration::from_secs(2)), 4);
+}
+
+#[test]
+fn test_frame_timer_caps_catch_up() {
+    let frame = Duration::from_secs(1) / 60;
+
+    let mut timer = FrameTimer::new(60, None);
+    assert_eq!(timer.frames(frame * 2 + frame / 2), 2);
+    assert_eq!(timer.frames(frame / 2), 1); // the two halves add up
+
+    // a two second hitch only runs 4 frames, and doesn't keep catching up afterwards
+    let mut timer = FrameTimer::new(60, Some(4));
+    assert_eq!(timer.frames(Duration::from_secs(2)), 4);
+    assert_eq!(timer.frames(frame), 1);
+    assert_eq!(timer.frames(Duration::ZERO), 0);
+}
+
+#[test]
+fn test_frames_within() {
+    assert_eq!(frames_within(2500, 1000), 2);
+    assert_eq!(frames_within(3000, 1000), 3);
+    // a limit under one frame still lets one through
+    assert_eq!(frames_within(10, 1000), 1);
+    assert_eq!(frames_within(100, 0), 100);
+
+    // a two second hitch at 1000 instructions a frame, capped at 2500 instructions
+    let mut timer = FrameTimer::new(60, Some(frames_within(2500, 1000)));
+    assert_eq!(timer.frames(Duration::from_secs(2)), 2);
+    assert_eq!(timer.frames(Duration::from_secs(1) / 60), 1);
+}
+
+#[test]
+fn test_instruction_budget_rollover() {
+    let mut budget = InstructionBudget::new(10, true);
+    assert_eq!(budget.next_frame(), 10);
+    budget.spend(4); // stopped early on a draw
+    assert_eq!(budget.next_frame(), 16);
+    budget.spend(16);
+    assert_eq!(budget.next_frame(), 10);
+    budget.spend(7);
+    budget.spend(3); // 13 given, 10 left over, but only 10 more can carry
+    assert_eq!(budget.next_frame(), 20);
+    budget.spend(0);
+    assert_eq!(budget.next_frame(), 20);
+
+    // over several frames, what ran plus what's still owed adds up to the target
+    let mut budget = InstructionBudget::new(10, true);
+    let mut ran = 0;
+    for frame in 0..6 {
+        let used = if frame % 2 == 0 {
+            5
+        } else {
+            budget.next_frame()
+        };
+        ran += used;
+        budget.spend(used);
+    }
+    assert_eq!(ran + budget.next_frame() - 10, 60);
+
+    // without adapting, nothing carries over
+    let mut budget = InstructionBudget::new(10, false);
+    budget.spend(2);
+    assert_eq!(budget.next_frame(), 10);
+}
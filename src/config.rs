@@ -0,0 +1,170 @@
+// per-ROM settings, read from a TOML file sitting next to the ROM.
+// `pong.ch8` picks up `pong.toml` if there is one,
+// and anything given on the command line wins over what's in it
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::keymap::button_named;
+use ggez::input::gamepad::gilrs::Button;
+use rite_emu::emu::Quirks;
+use serde::{de, Deserialize, Deserializer};
+
+/// Settings remembered for a single ROM.
+/// Anything left out of the file stays at its default.
+///
+/// # Fields
+/// * `quirks` - a preset name like `"vip"`, or a table of individual quirks
+/// * `cycles_per_frame` - instructions to run every 60 FPS frame
+/// * `colors` - what lit and unlit pixels look like
+/// * `keymap` - CHIP-8 keys (as a hex digit) moved to other scancodes
+/// * `gamepad` - CHIP-8 keys (as a hex digit) moved to other gamepad buttons, by name
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(deserialize_with = "quirks_or_preset")]
+    pub quirks: Option<Quirks>,
+    pub cycles_per_frame: Option<u32>,
+    pub colors: Colors,
+    #[serde(deserialize_with = "keymap_entries")]
+    pub keymap: BTreeMap<usize, u32>,
+    #[serde(deserialize_with = "gamepad_entries")]
+    pub gamepad: BTreeMap<usize, Button>,
+}
+
+/// Pixel colors as `[red, green, blue]`, `None` to keep the usual white on black.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub on: Option<[u8; 3]>,
+    pub off: Option<[u8; 3]>,
+}
+
+impl Config {
+    /// reads the config for the ROM at `rom_path`,
+    /// warning and falling back to defaults if it's missing or broken
+    pub fn load(rom_path: &Path) -> Config {
+        let path = rom_path.with_extension("toml");
+        let text = match read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                println!("no config at {} ({}), using defaults", path.display(), e);
+                return Config::default();
+            }
+        };
+        Config::parse(&text).unwrap_or_else(|message| {
+            println!("ignoring config at {}: {}", path.display(), message);
+            Config::default()
+        })
+    }
+
+    /// parses the contents of a config file
+    pub fn parse(text: &str) -> Result<Config, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+}
+
+/// lets `quirks` be either the name of a preset or a table spelling them out
+fn quirks_or_preset<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Quirks>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum QuirksSetting {
+        Preset(String),
+        Custom(Quirks),
+    }
+
+    match QuirksSetting::deserialize(deserializer)? {
+        QuirksSetting::Preset(name) => Quirks::preset(&name)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("unknown quirks preset {}", name))),
+        QuirksSetting::Custom(quirks) => Ok(Some(quirks)),
+    }
+}
+
+/// turns the hex digit keys of the `keymap` table into keypad indexes
+fn keymap_entries<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<usize, u32>, D::Error> {
+    let entries = BTreeMap::<String, u32>::deserialize(deserializer)?;
+    entries
+        .into_iter()
+        .map(|(key, scancode)| Ok((keypad_index(&key)?, scancode)))
+        .collect()
+}
+
+/// the same as `keymap_entries`, but for the button names in the `gamepad` table
+fn gamepad_entries<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<usize, Button>, D::Error> {
+    let entries = BTreeMap::<String, String>::deserialize(deserializer)?;
+    entries
+        .into_iter()
+        .map(|(key, name)| match button_named(&name) {
+            Some(button) => Ok((keypad_index(&key)?, button)),
+            None => Err(de::Error::custom(format!(
+                "{} isn't a gamepad button",
+                name
+            ))),
+        })
+        .collect()
+}
+
+/// a CHIP-8 key written as a single hex digit
+fn keypad_index<E: de::Error>(key: &str) -> Result<usize, E> {
+    match usize::from_str_radix(key, 16) {
+        Ok(index) if key.len() == 1 => Ok(index),
+        _ => Err(E::custom(format!("{} isn't a CHIP-8 key", key))),
+    }
+}
+
+#[test]
+fn test_parse_config() {
+    use rite_emu::emu::{LoadStoreBehavior, ZeroOpcodeBehavior};
+
+    let config = Config::parse(
+        r#"
+        cycles_per_frame = 20
+
+        [quirks]
+        zero_opcode_behavior = "halt"
+        load_store = "cosmac"
+
+        [colors]
+        on = [255, 176, 0]
+
+        [keymap]
+        5 = 0x48
+        a = 0x1c
+
+        [gamepad]
+        1 = "start"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.cycles_per_frame, Some(20));
+    assert_eq!(
+        config.quirks,
+        Some(Quirks {
+            zero_opcode_behavior: ZeroOpcodeBehavior::Halt,
+            load_store: LoadStoreBehavior::Cosmac,
+            ..Quirks::default()
+        })
+    );
+    assert_eq!(config.colors.on, Some([255, 176, 0]));
+    assert_eq!(config.colors.off, None);
+    assert_eq!(config.keymap, BTreeMap::from([(0x5, 0x48), (0xa, 0x1c)]));
+    assert_eq!(config.gamepad, BTreeMap::from([(0x1, Button::Start)]));
+
+    let preset = Config::parse("quirks = \"chip48\"").unwrap();
+    assert_eq!(preset.quirks, Some(Quirks::chip48()));
+    assert_eq!(Config::parse("").unwrap(), Config::default());
+
+    assert!(Config::parse("quirks = \"amiga\"").is_err());
+    assert!(Config::parse("[keymap]\n10 = 0x02").is_err());
+    assert!(Config::parse("[gamepad]\n1 = \"turbo\"").is_err());
+    assert!(Config::parse("cycles_per_frame = \"fast\"").is_err());
+}
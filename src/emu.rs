@@ -1,23 +1,51 @@
-use rand::Rng;
-use std::fmt::{Debug, Display};
+use crate::instruction::{decode, pattern_for, supported_opcodes, variant_for, Instruction};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display, Write};
 
 pub enum EmulationError {
-    StackOverflow,      // emulated stack exceeds 16 entries
-    LoadingError, // invoked when the ROM tried to load is larger than 4 kB, or something else happens
+    StackOverflow,                     // emulated stack exceeds 16 entries
+    StackUnderflow,                    // returned from a subroutine without ever calling one
+    LoadingError, // the ROM is too big for memory, or a memory image isn't the size of memory
     VacantMemory, // invoked when we run into a sequence of 0000s or similar
     UnknownInstruction, // ran into an instruction that looks kind of valid, but isn't ultimately
+    InvalidRegister, // a register held a value that the instruction using it can't make sense of
+    FramebufferSize, // framebuffer data given to us doesn't match the size of the screen
+    InvalidAddress, // an instruction tried to use memory past the end of it
+    InstructionDenied { opcode: u16 }, // ran an instruction that's been turned off with `Emu::deny`
+    OutsidePlatform { opcode: u16 }, // ran an instruction the platform from `EmuBuilder::platform` doesn't have
+    EmptyRom,                        // the ROM given to `read_rom` had nothing in it
 }
 
 impl Debug for EmulationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::StackOverflow => write!(f, "emulated stack overflowed"),
-            Self::LoadingError => write!(f, "ROM failed to load, file likely exceeds 4 kB"),
+            Self::StackUnderflow => write!(f, "returned with nothing on the emulated stack"),
+            Self::LoadingError => write!(f, "ROM or memory image doesn't fit in memory"),
+            Self::EmptyRom => write!(f, "ROM file is empty"),
             Self::VacantMemory => write!(
                 f,
                 "ROM ran out of memory and encountered an instruction like 0000"
             ),
             Self::UnknownInstruction => write!(f, "ran into an unrecognized instruction"),
+            Self::InvalidRegister => write!(f, "a register held a value the instruction can't use"),
+            Self::FramebufferSize => write!(f, "framebuffer data doesn't match the screen size"),
+            Self::InvalidAddress => {
+                write!(f, "an instruction used an address past the end of memory")
+            }
+            Self::InstructionDenied { opcode } => {
+                write!(f, "ran {:04X}, which isn't allowed to run", opcode)
+            }
+            Self::OutsidePlatform { opcode } => {
+                write!(
+                    f,
+                    "ran {:04X}, which the chosen platform doesn't have",
+                    opcode
+                )
+            }
         }
     }
 }
@@ -26,41 +54,519 @@ impl Display for EmulationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::StackOverflow => write!(f, "emulated stack overflowed"),
-            Self::LoadingError => write!(f, "ROM failed to load, file likely exceeds 4 kB"),
+            Self::StackUnderflow => write!(f, "returned with nothing on the emulated stack"),
+            Self::LoadingError => write!(f, "ROM or memory image doesn't fit in memory"),
+            Self::EmptyRom => write!(f, "ROM file is empty"),
             Self::VacantMemory => write!(
                 f,
                 "ROM ran out of memory and encountered an instruction like 0000"
             ),
             Self::UnknownInstruction => write!(f, "ran into an unrecognized instruction"),
+            Self::InvalidRegister => write!(f, "a register held a value the instruction can't use"),
+            Self::FramebufferSize => write!(f, "framebuffer data doesn't match the screen size"),
+            Self::InvalidAddress => {
+                write!(f, "an instruction used an address past the end of memory")
+            }
+            Self::InstructionDenied { opcode } => {
+                write!(f, "ran {:04X}, which isn't allowed to run", opcode)
+            }
+            Self::OutsidePlatform { opcode } => {
+                write!(
+                    f,
+                    "ran {:04X}, which the chosen platform doesn't have",
+                    opcode
+                )
+            }
+        }
+    }
+}
+
+/// What to do when the emulator runs into a `0000` opcode.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ZeroOpcodeBehavior {
+    #[default]
+    Error, // return `EmulationError::VacantMemory`
+    Nop,  // skip over it and keep going
+    Halt, // stop executing without treating it as an error
+}
+
+/// What `FX55` and `FX65` do to the index register after they're done.
+/// Which registers get copied where is the same for all of them.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadStoreBehavior {
+    #[default]
+    Unchanged, // I is left alone, like SUPER-CHIP 1.1 and most modern interpreters
+    Cosmac, // I ends up at I + X + 1, one past the last address used, like the COSMAC VIP
+    Hp48,   // I ends up at I + X, one short of the COSMAC VIP, like CHIP-48 on the HP48
+}
+
+/// Configuration for the behaviors that differ between CHIP-8 implementations.
+/// `Quirks::default()` gives the behavior rite-emu has always had.
+///
+/// # Fields
+/// * `zero_opcode_behavior` - how a `0000` opcode is handled
+/// * `strict_font` - `FX29` errors on values above `0xF` instead of masking them to one hex digit
+/// * `strict_keys` - `EX9E` and `EXA1` error on keys above `0xF` instead of masking them to one hex digit
+///   like the hardware does, for catching ROM bugs
+/// * `load_store` - what `FX55` and `FX65` leave in the index register
+/// * `clip_counter` - in hires, `DXYN` sets `VF` to the number of rows that collided
+///   plus the rows clipped off the bottom, like SUPER-CHIP 1.1, instead of just 0 or 1.
+///   No commonly played games are known to need this, it's there for matching
+///   SUPER-CHIP 1.1 exactly (and the test ROMs that check for it)
+/// * `count_collided_rows` - in hires, `DXYN` sets `VF` to the number of rows that collided,
+///   without the clipped ones, like some other SUPER-CHIP interpreters.
+///   So there are three ways `VF` can end up after a hires draw: 0 or 1 (neither quirk),
+///   the collided rows (this one), or the collided plus clipped rows (`clip_counter`, which wins if both are on).
+///   Lores draws always give 0 or 1
+/// * `timers_before_cpu` - timers count down at the start of each frame instead of the end.
+///   The timers tick on the display's vertical blank, which comes after a frame's
+///   instructions have run, so by default an `FX07` sees the value from before the tick
+/// * `jump_uses_vx` - `BNNN` acts as `BXNN`, jumping to `XNN` plus `VX` instead of `NNN` plus `V0`,
+///   like CHIP-48 and SUPER-CHIP
+/// * `latch_key_taps` - `EX9E`, `EXA1`, and `FX0A` see a key pressed at any point during the frame
+///   as down, even if it was let go before they ran
+/// * `clamp_sprite_start` - `DXYN` starting coordinates past the edge of the screen are moved
+///   to the last row or column instead of wrapping around, which hardly any ROMs expect
+/// * `display_wait` - `DXYN` waits for the next frame before anything else runs, like the COSMAC VIP
+/// * `shift_uses_vy` - `8XY6` and `8XYE` shift `VY` into `VX`, like the COSMAC VIP,
+///   instead of shifting `VX` in place. Either way `VF` gets the bit shifted out of the value that was shifted
+/// * `clear_on_resolution_change` - `00FE` and `00FF` clear the screen, like most modern interpreters.
+///   When off, whatever was on the screen gets scaled up or down to the new resolution instead
+/// * `beep_above_one` - a sound timer of 1 stays silent, like on the COSMAC VIP
+///   where it runs out before making any sound
+/// * `skip_ignores_n` - `5XYN` skips when `VX` equals `VY` whatever `N` is, like some lenient interpreters,
+///   instead of only `5XY0` being a known instruction. For ROMs that got the last digit wrong
+/// * `add_immediate_sets_vf` - `7XNN` sets `VF` to the carry like `8XY4` does. This isn't standard,
+///   `7XNN` is meant to leave `VF` alone, but a few clones got it wrong and ROMs written on them rely on it
+/// * `lenient_8xyn` - `8XYN` with an `N` that isn't a known instruction, like `8XY8`, does nothing
+///   instead of being an error, like on some hardware ROMs were written for. Strict by default
+/// * `skip_long_load` - skip instructions skip all 4 bytes of an XO-CHIP `F000 NNNN` instead of landing
+///   in the middle of it, like XO-CHIP. Off by default since `F000` is only an instruction there,
+///   anywhere else it's just data that happens to look like one
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Quirks {
+    pub zero_opcode_behavior: ZeroOpcodeBehavior,
+    pub strict_font: bool,
+    pub strict_keys: bool,
+    pub load_store: LoadStoreBehavior,
+    pub display_wait: bool,
+    pub shift_uses_vy: bool,
+    pub clip_counter: bool,
+    pub count_collided_rows: bool,
+    pub clamp_sprite_start: bool,
+    pub timers_before_cpu: bool,
+    pub jump_uses_vx: bool,
+    pub latch_key_taps: bool,
+    pub clear_on_resolution_change: bool,
+    pub beep_above_one: bool,
+    pub skip_ignores_n: bool,
+    pub add_immediate_sets_vf: bool,
+    pub lenient_8xyn: bool,
+    pub skip_long_load: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            zero_opcode_behavior: ZeroOpcodeBehavior::default(),
+            strict_font: false,
+            strict_keys: false,
+            load_store: LoadStoreBehavior::default(),
+            display_wait: false,
+            shift_uses_vy: false,
+            clip_counter: false,
+            count_collided_rows: false,
+            clamp_sprite_start: false,
+            timers_before_cpu: false,
+            jump_uses_vx: false,
+            latch_key_taps: false,
+            clear_on_resolution_change: true,
+            beep_above_one: false,
+            skip_ignores_n: false,
+            add_immediate_sets_vf: false,
+            lenient_8xyn: false,
+            skip_long_load: false,
+        }
+    }
+}
+
+impl Quirks {
+    /// behaves like the original COSMAC VIP interpreter
+    pub fn cosmac_vip() -> Self {
+        Quirks {
+            load_store: LoadStoreBehavior::Cosmac,
+            display_wait: true,
+            shift_uses_vy: true,
+            beep_above_one: true,
+            ..Quirks::default()
+        }
+    }
+    /// behaves like CHIP-48 on the HP48 calculators
+    pub fn chip48() -> Self {
+        Quirks {
+            load_store: LoadStoreBehavior::Hp48,
+            jump_uses_vx: true,
+            ..Quirks::default()
+        }
+    }
+    /// behaves like SUPER-CHIP 1.1
+    pub fn schip() -> Self {
+        Quirks {
+            load_store: LoadStoreBehavior::Unchanged,
+            jump_uses_vx: true,
+            ..Quirks::default()
+        }
+    }
+    /// looks up a preset by the name used on the command line
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "vip" => Some(Self::cosmac_vip()),
+            "chip48" => Some(Self::chip48()),
+            "schip" => Some(Self::schip()),
+            _ => None,
+        }
+    }
+}
+
+/// A machine to hold ROMs to strictly, see `EmuBuilder::platform`.
+/// Each one has every instruction of the ones before it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Platform {
+    Chip8,  // only the original COSMAC VIP instructions
+    Schip,  // adds the SUPER-CHIP 1.1 instructions, like hires and big sprites
+    XoChip, // adds the XO-CHIP instructions on top of those
+}
+
+impl Platform {
+    /// whether this platform has the instructions from `variant`,
+    /// in the naming used by `supported_opcodes`
+    pub fn allows(self, variant: &str) -> bool {
+        match variant {
+            "CHIP-8" => true,
+            "SUPER-CHIP" => self != Platform::Chip8,
+            _ => self == Platform::XoChip,
         }
     }
 }
 
+/// How `DXYN` combines a sprite with what's already on the screen.
+/// Only `Xor` is real CHIP-8, `Or` is for looking at sprite data while debugging.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DrawMode {
+    #[default]
+    Xor, // sprite pixels flip the screen's pixels
+    Or, // sprite pixels only ever turn the screen's pixels on, and VF is always left at 0
+}
+
+/// Why the frontend should, or shouldn't, keep running instructions this frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepResult {
+    Normal,         // carry on
+    AwaitingVBlank, // drew with `quirks.display_wait` on, nothing more until the next frame
+    AwaitingKey,    // stuck on `FX0A` until a key is pressed
+}
+
+/// Something whose value the emulator keeps an eye on, see `Emu::add_watch`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WatchTarget {
+    Register(u8), // one of V0 through VF
+    Memory(u16),  // a single byte of memory
+}
+
+/// A watched value that changed.
+///
+/// # Fields
+/// * `target` - what changed
+/// * `pc` - address of the instruction that changed it
+/// * `old` - the value before the instruction ran
+/// * `new` - the value after the instruction ran
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatchHit {
+    pub target: WatchTarget,
+    pub pc: u16,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// width and height of the normal CHIP-8 screen
+pub const LORES_SIZE: (usize, usize) = (64, 32);
+/// width and height of the SUPER-CHIP high resolution screen
+pub const HIRES_SIZE: (usize, usize) = (128, 64);
+
+/// how far past the instruction doing a write still counts as code that's about to run,
+/// 8 instructions' worth
+const SELF_MODIFY_WINDOW: u16 = 16;
+
+/// bytes of memory on a standard CHIP-8
+pub const MEMORY_SIZE: usize = 4096;
+/// bytes of memory in XO-CHIP mode, everything a 16-bit address can reach
+pub const XO_CHIP_MEMORY_SIZE: usize = 65536;
+/// where ROMs get loaded, everything below is reserved for the interpreter and font
+const PROGRAM_START: usize = 0x200;
+
+/// Where `CXNN` gets its random numbers from, see `Emu::set_random_source`.
+/// Normally a seeded `StdRng`, but tests can hand in one that returns exactly what they want.
+pub trait RandomSource {
+    /// the next random byte
+    fn next_byte(&mut self) -> u8;
+}
+
+impl RandomSource for StdRng {
+    fn next_byte(&mut self) -> u8 {
+        self.gen()
+    }
+}
+
+/// Gets the screen's pixels, width, and height at the end of every frame,
+/// see `Emu::set_frame_callback`.
+pub type FrameCallback = Box<dyn FnMut(&[bool], usize, usize)>;
+
+/// Gets the whole emulator at the end of every frame, to change whatever it likes,
+/// see `Emu::set_post_frame_hook`.
+pub type PostFrameHook = Box<dyn FnMut(&mut Emu)>;
+
+/// A write into code that's about to run, see `Emu::detect_self_modifying`.
+///
+/// # Fields
+/// * `address` - the address written to
+/// * `pc` - address of the instruction that wrote it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SelfModifyHit {
+    pub address: u16,
+    pub pc: u16,
+}
+
+/// A pixel that a `DXYN` or `00E0` changed, see `Emu::log_pixel_changes`.
+///
+/// # Fields
+/// * `x` - column of the pixel
+/// * `y` - row of the pixel
+/// * `lit` - whether the pixel got turned on, instead of off
+/// * `pc` - address of the instruction that changed it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelChange {
+    pub x: usize,
+    pub y: usize,
+    pub lit: bool,
+    pub pc: u16,
+}
+
 /// Represents the actual emulation of a CHIP-8 system.
 ///
 /// # Fields
-/// * `pixels` - public field representing on/off data for the screen's pixels
+/// * `pixels` - on/off data for the screen's pixels, read it with `Emu::pixels`
 /// * `the_stack` - stack for 16-bit addresses
-/// * `memory` - 4 kB of memory, represented as a `Vec<u8>`
+/// * `memory` - 4 kB of memory (64 kB in XO-CHIP mode), represented as a `Vec<u8>`
 /// * `pc` - the program counter, decodes to current instruction in memory
 /// * `i` - index register, points at various locations in memory
 /// * `delay_timer` - weird delay thing that CHIP-8 programs use
 /// * `sound_timer` - like `delay_timer` but for sound
 /// * `variables` - 16 one byte variable registers
+/// * `keys` - held state of each of the 16 keys
+/// * `tapped` - keys pressed at some point this frame, even if they've been let go already
+/// * `quirks` - public field for which implementation behaviors to follow
+/// * `halted` - set once the ROM has stopped itself, no more instructions will run
+/// * `dirty` - set whenever the screen changes, until `take_dirty` is called
+/// * `hires` - whether the SUPER-CHIP 128 x 64 screen is in use instead of 64 x 32
+/// * `decay` - brightness of each pixel for phosphor decay, `None` unless turned on
+/// * `decay_step` - how much brightness a pixel loses each frame after turning off
+/// * `watches` - watched values, along with the value each had last time we looked
+/// * `watch_hits` - changes to watched values that haven't been taken yet
+/// * `self_modify_hits` - writes into upcoming code that haven't been taken yet, `None` unless detecting them
+/// * `pixel_changes` - pixels drawing changed that haven't been taken yet, `None` unless logging them
+/// * `lint` - whether to warn about things ROMs probably didn't mean to do, see `Emu::lint`
+/// * `warned_zero_mask` - a `CXNN` with `NN` of 0 has already been warned about
+/// * `warnings` - warnings from `lint` that haven't been taken yet
+/// * `rng` - where `CXNN` gets its random numbers from
+/// * `breakpoints` - addresses a debugger wants to stop at
+/// * `preserve_memory` - loading a ROM leaves whatever was past its end alone instead of zeroing it
+/// * `frame_callback` - handed the screen after every frame, see `Emu::set_frame_callback`
+/// * `post_frame_hook` - handed the emulator after every frame, see `Emu::set_post_frame_hook`
+/// * `draw_mode` - how sprites get drawn, see `Emu::set_draw_mode`
+/// * `denied` - opcode patterns that aren't allowed to run, see `Emu::deny`
+/// * `frame_count` - how many frames `step_frame` has finished
+/// * `platform` - the only platform whose instructions are allowed to run, `None` for any of them
+/// * `drew_anything` - set the first time a `DXYN` runs
+/// * `history` - address and opcode of the last few instructions run, oldest first
+/// * `history_len` - how many instructions `history` holds on to, 0 to not keep any
+/// * `paused` - set while the user has paused, see `Emu::set_paused`
+/// * `paused_keys` - key presses (`true`) and releases (`false`) that came in while paused, oldest first
+/// * `deferred_releases` - keys tapped while paused, let go at the end of the first frame after
+/// * `initial_timers` - delay and sound timer values a ROM starts with, see `EmuBuilder::delay_timer`
+/// * `memory_fill` - what memory outside the font and ROM holds, see `EmuBuilder::memory_fill`
+/// * `initial_keys` - keys held down whenever a ROM starts, see `EmuBuilder::initial_keys`
 pub struct Emu {
-    pub pixels: Vec<bool>, // true if on, false if off.
-    the_stack: Vec<u16>,   // stack for 16-bit addresses
-    memory: Vec<u8>,       // memory; should really only be up to 4 kB large
-    pc: u16,               // program counter, points to current instruction in memory
-    i: u16,                // index register, points at locations in memory
-    delay_timer: u8,       // decremented at a rate of 60 Hz until it reaches zero
-    sound_timer: u8,       // functions like the delay timer, but gives a beeping noise
+    pixels: Vec<bool>,   // true if on, false if off.
+    the_stack: Vec<u16>, // stack for 16-bit addresses
+    memory: Vec<u8>,     // memory; 4 kB large, unless built for XO-CHIP
+    pc: u16,             // program counter, points to current instruction in memory
+    i: u16,              // index register, points at locations in memory
+    delay_timer: u8,     // decremented at a rate of 60 Hz until it reaches zero
+    sound_timer: u8,     // functions like the delay timer, but gives a beeping noise
     // as long as it isn't 0
     variables: Vec<u8>, // 16 variable registers- could be represented instead with 0 - F.
     // F (the last register) is used as a flag register,
     // i.e. instructions may set it to 1 or 0 from some rule.
     keys: Vec<bool>, // represent each of the 16 keys,
-                     // reflects true if this key is held down and false if otherwise
+    // reflects true if this key is held down and false if otherwise
+    tapped: Vec<bool>,
+    pub quirks: Quirks,
+    halted: bool,
+    dirty: bool,
+    hires: bool,
+    decay: Option<Vec<u8>>,
+    decay_step: u8,
+    watches: Vec<(WatchTarget, u8)>,
+    watch_hits: Vec<WatchHit>,
+    self_modify_hits: Option<Vec<SelfModifyHit>>,
+    pixel_changes: Option<Vec<PixelChange>>,
+    lint: bool,
+    warned_zero_mask: bool,
+    warnings: Vec<String>,
+    rng: Box<dyn RandomSource>,
+    breakpoints: Vec<u16>,
+    preserve_memory: bool,
+    frame_callback: Option<FrameCallback>,
+    post_frame_hook: Option<PostFrameHook>,
+    draw_mode: DrawMode,
+    denied: Vec<&'static str>,
+    frame_count: u64,
+    platform: Option<Platform>,
+    drew_anything: bool,
+    history: VecDeque<(u16, u16)>,
+    history_len: usize,
+    paused: bool,
+    paused_keys: Vec<(usize, bool)>,
+    deferred_releases: Vec<usize>,
+    initial_timers: (u8, u8),
+    memory_fill: u8,
+    initial_keys: u16,
+}
+
+impl Default for Emu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sets up an `Emu` with options that decide how the machine itself is laid out,
+/// start one with `Emu::builder()`.
+///
+/// # Fields
+/// * `xo_chip` - give the machine 64 kB of memory like XO-CHIP instead of 4 kB
+/// * `memory_bytes` - exactly how much memory to give the machine, `None` to go by `xo_chip`
+/// * `platform` - see `EmuBuilder::platform`
+/// * `initial_keys` - keys held down from the start, bit `n` for key `n`
+/// * `delay_timer` - what the delay timer starts at
+/// * `sound_timer` - what the sound timer starts at
+/// * `memory_fill` - what every byte of memory holds before the font and ROM are loaded
+/// * `quirks` - which implementation behaviors to follow
+/// * `preserve_memory` - see `Emu::read_rom`
+#[derive(Default)]
+pub struct EmuBuilder {
+    xo_chip: bool,
+    memory_bytes: Option<usize>,
+    platform: Option<Platform>,
+    initial_keys: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+    memory_fill: u8,
+    quirks: Quirks,
+    preserve_memory: bool,
+}
+
+impl EmuBuilder {
+    /// whether to build an XO-CHIP sized machine
+    pub fn xo_chip(mut self, xo_chip: bool) -> Self {
+        self.xo_chip = xo_chip;
+        self
+    }
+
+    /// how many bytes of memory to give the machine, for interpreters with sizes other than 4 kB or 64 kB.
+    /// Takes precedence over `xo_chip`. Sizes outside of 514 bytes (room for one instruction
+    /// where programs start) to the 64 kB that 16-bit addresses can reach are clamped to the nearest
+    /// of the two, so `memory_bytes(1 << 20)` gives a 64 kB machine.
+    /// Memory isn't mirrored past the end, beyond addresses wrapping around for power of two sizes
+    pub fn memory_bytes(mut self, memory_bytes: usize) -> Self {
+        self.memory_bytes = Some(memory_bytes.clamp(PROGRAM_START + 2, XO_CHIP_MEMORY_SIZE));
+        self
+    }
+
+    /// only lets instructions from `platform` run, so a ROM using anything newer
+    /// fails right away with `EmulationError::OutsidePlatform` instead of misbehaving
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// which keys are already held down when the `Emu` starts, bit `n` for key `n`,
+    /// for replaying a run that started with a key held
+    pub fn initial_keys(mut self, mask: u16) -> Self {
+        self.initial_keys = mask;
+        self
+    }
+
+    /// what the delay timer starts at, instead of 0, for starting a machine partway through a countdown.
+    /// Loading a ROM starts it here again
+    pub fn delay_timer(mut self, value: u8) -> Self {
+        self.delay_timer = value;
+        self
+    }
+
+    /// what the sound timer starts at, like `delay_timer`
+    pub fn sound_timer(mut self, value: u8) -> Self {
+        self.sound_timer = value;
+        self
+    }
+
+    /// what memory holds anywhere the font and ROM don't, instead of 0,
+    /// like `0xFF` for machines that powered up with every bit set.
+    /// ROMs that read memory they never wrote and expect 0 show themselves quickly this way
+    pub fn memory_fill(mut self, byte: u8) -> Self {
+        self.memory_fill = byte;
+        self
+    }
+
+    /// which quirks the `Emu` starts out with
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// keep memory from earlier ROMs past the end of a newly loaded one,
+    /// instead of clearing the whole program region
+    pub fn preserve_memory(mut self, preserve_memory: bool) -> Self {
+        self.preserve_memory = preserve_memory;
+        self
+    }
+
+    /// the same as `Emu::new`, but with everything set up here applied
+    pub fn build(self) -> Emu {
+        let memory_size = match self.memory_bytes {
+            Some(memory_bytes) => memory_bytes,
+            None if self.xo_chip => XO_CHIP_MEMORY_SIZE,
+            None => MEMORY_SIZE,
+        };
+        let mut emulator = Emu::with_memory(memory_size, self.memory_fill);
+        emulator.quirks = self.quirks;
+        emulator.preserve_memory = self.preserve_memory;
+        emulator.platform = self.platform;
+        emulator.initial_keys = self.initial_keys;
+        emulator.hold_initial_keys();
+        emulator.initial_timers = (self.delay_timer, self.sound_timer);
+        (emulator.delay_timer, emulator.sound_timer) = emulator.initial_timers;
+        emulator
+    }
+
+    /// the same as `build`, then loading `rom` with `Emu::read_rom`
+    pub fn build_with_rom(self, rom: &[u8]) -> Result<Emu, EmulationError> {
+        let mut emulator = self.build();
+        emulator.read_rom(rom.to_vec())?;
+        Ok(emulator)
+    }
 }
 
 impl Emu {
@@ -68,7 +574,33 @@ impl Emu {
     /// Everything is initialized to the basic emulation environment,
     /// but without the actual program.
     pub fn new() -> Self {
-        let mut memory: Vec<u8> = vec![0; 4096];
+        Self::with_memory(MEMORY_SIZE, 0)
+    }
+
+    /// the same as `Emu::new` followed by `Emu::read_rom`, for when the defaults will do.
+    ///
+    /// ```
+    /// use rite_emu::emu::Emu;
+    ///
+    /// // LD V0, 0x2A; JP 0x202
+    /// let mut emulator = Emu::from_rom(&[0x60, 0x2a, 0x12, 0x02]).unwrap();
+    /// emulator.step_frame(10).unwrap();
+    /// assert_eq!(emulator.pc(), 0x202);
+    /// ```
+    pub fn from_rom(rom: &[u8]) -> Result<Self, EmulationError> {
+        Emu::builder().build_with_rom(rom)
+    }
+
+    /// starts setting up an `Emu` with options that have to be known
+    /// before it exists, see `EmuBuilder`
+    pub fn builder() -> EmuBuilder {
+        EmuBuilder::default()
+    }
+
+    /// the same as `Emu::new`, but with `size` bytes of memory,
+    /// all `memory_fill` but for the font
+    fn with_memory(size: usize, memory_fill: u8) -> Self {
+        let mut memory: Vec<u8> = vec![memory_fill; size];
 
         // font stuff. this is a LOT of hex,
         // but this is basically just the standard font to use with CHIP-8.
@@ -93,15 +625,11 @@ impl Emu {
             0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
             0xF0, 0x80, 0xF0, 0x80, 0x80, // F
         ];
-        let mut fonts_iter = fonts.iter();
-        for i in 0x050..0x09F {
-            if let Some(hex) = fonts_iter.next() {
-                memory[i] = *hex;
-            }
-        }
+        // 16 characters of 5 bytes each, so the font ends at 0x09F inclusive
+        memory[0x050..0x0A0].copy_from_slice(&fonts);
 
         Emu {
-            pixels: vec![false; 64 * 32], // display is 32 by 64
+            pixels: vec![false; LORES_SIZE.0 * LORES_SIZE.1], // display is 32 by 64
             the_stack: vec![],
             memory,
             // maybe change these later VVV
@@ -111,21 +639,128 @@ impl Emu {
             sound_timer: 0,
             variables: vec![0; 16], // should always have only 16 elements
             keys: vec![false; 16],  // only 16 keys;
-                                    // the text printed on the original COSMAC VIP layout
-                                    // corresponds to its index in this vector
+            tapped: vec![false; 16],
+            // the text printed on the original COSMAC VIP layout
+            // corresponds to its index in this vector
+            quirks: Quirks::default(),
+            halted: false,
+            dirty: false,
+            hires: false,
+            decay: None,
+            decay_step: 0,
+            watches: vec![],
+            watch_hits: vec![],
+            self_modify_hits: None,
+            pixel_changes: None,
+            lint: false,
+            warned_zero_mask: false,
+            warnings: vec![],
+            rng: Box::new(StdRng::from_entropy()),
+            breakpoints: vec![],
+            preserve_memory: false,
+            frame_callback: None,
+            post_frame_hook: None,
+            draw_mode: DrawMode::default(),
+            denied: vec![],
+            frame_count: 0,
+            platform: None,
+            drew_anything: false,
+            history: VecDeque::new(),
+            history_len: 0,
+            paused: false,
+            paused_keys: vec![],
+            deferred_releases: vec![],
+            initial_timers: (0, 0),
+            memory_fill,
+            initial_keys: 0,
+        }
+    }
+
+    /// returns an instance of Emu with exactly the given state,
+    /// for setting up a machine partway through running something.
+    /// `memory` starts at address 0 (so it replaces the font too),
+    /// and is padded with zeros up to 4 kB.
+    /// Anything not given starts out the same as in `Emu::new`.
+    pub fn with_state(
+        pc: u16,
+        i: u16,
+        registers: [u8; 16],
+        mut memory: Vec<u8>,
+    ) -> Result<Emu, EmulationError> {
+        if memory.len() > MEMORY_SIZE {
+            return Err(EmulationError::LoadingError);
         }
+        memory.resize(MEMORY_SIZE, 0);
+
+        let mut emulator = Emu::new();
+        emulator.pc = pc;
+        emulator.i = i;
+        emulator.variables = registers.to_vec();
+        emulator.memory = memory;
+        Ok(emulator)
     }
 
+    /// copies `rom` into memory where programs start.
+    /// Fails if it doesn't fit in what's left after the reserved region,
+    /// which depends on how much memory this `Emu` was built with.
+    ///
+    /// Unless built with `preserve_memory`, everything from where programs start onward
+    /// is zeroed first (or set to `EmuBuilder::memory_fill`),
+    /// so nothing left over from an earlier ROM gets run as part of this one.
+    /// The font below it is left alone either way.
+    ///
+    /// Everything else about the machine starts over too, so loading a ROM over another one
+    /// behaves like a fresh start: registers, stack, timers, keys, and the screen are cleared
+    /// and PC goes back to where programs start. Quirks and other settings stay.
     pub fn read_rom(&mut self, rom: Vec<u8>) -> Result<(), EmulationError> {
-        let mut rom_iter = rom.iter();
-        let mut current_address = 0x200;
-        while let Some(data) = rom_iter.next() {
-            if current_address >= 4096 {
-                return Err(EmulationError::LoadingError);
-            }
-            self.memory[current_address] = *data;
-            current_address += 1;
+        if rom.is_empty() {
+            // there'd be nothing to run but empty memory
+            return Err(EmulationError::EmptyRom);
+        }
+        if rom.len() > self.memory.len() - PROGRAM_START {
+            return Err(EmulationError::LoadingError);
+        }
+        if !self.preserve_memory {
+            self.memory[PROGRAM_START..].fill(self.memory_fill);
+        }
+        self.memory[PROGRAM_START..PROGRAM_START + rom.len()].copy_from_slice(&rom);
+        self.reset_machine();
+        Ok(())
+    }
+
+    /// puts the CPU and screen back the way `Emu::new` leaves them, without touching memory
+    fn reset_machine(&mut self) {
+        self.pc = PROGRAM_START as u16;
+        self.i = 0;
+        self.variables = vec![0; 16];
+        self.the_stack.clear();
+        (self.delay_timer, self.sound_timer) = self.initial_timers;
+        self.halted = false;
+        self.frame_count = 0;
+        self.drew_anything = false;
+        self.warned_zero_mask = false;
+        self.hires = false;
+        self.hold_initial_keys();
+        self.clear_screen().unwrap_or_default();
+        if let Some(decay) = &mut self.decay {
+            *decay = vec![0; self.pixels.len()];
+        }
+        // a new ROM hasn't changed anything yet
+        for index in 0..self.watches.len() {
+            self.watches[index].1 = self.watched_value(self.watches[index].0);
         }
+    }
+
+    /// replaces all of memory with `image`, starting from address 0
+    /// (so it replaces the font too), and points PC at where programs start.
+    /// `image` has to be exactly as big as memory.
+    /// Mostly meant for throwing random memory at the emulator to make sure it never panics.
+    pub fn load_memory_image(&mut self, image: &[u8]) -> Result<(), EmulationError> {
+        if image.len() != self.memory.len() {
+            return Err(EmulationError::LoadingError);
+        }
+        self.memory.copy_from_slice(image);
+        self.pc = PROGRAM_START as u16;
         Ok(())
     }
 
@@ -145,12 +780,8 @@ impl Emu {
     /// Pops from the stack.
     /// I just think this is nicer within the context of OOP,
     /// given the presence of `stack_push`.
-    fn stack_pop(&mut self) -> u16 {
-        if let Some(val) = self.the_stack.pop() {
-            val
-        } else {
-            0 // maybe revisit this alternative return value
-        }
+    fn stack_pop(&mut self) -> Result<u16, EmulationError> {
+        self.the_stack.pop().ok_or(EmulationError::StackUnderflow)
     }
 
     /// Decrements `delay_timer`,
@@ -167,26 +798,174 @@ impl Emu {
             self.sound_timer -= 1;
         }
     }
+    /// true while the sound timer is running,
+    /// which is when the frontend should be beeping.
+    /// With `quirks.beep_above_one` the last tick of the timer doesn't count
+    pub fn is_beeping(&self) -> bool {
+        let threshold = if self.quirks.beep_above_one { 1 } else { 0 };
+        self.sound_timer > threshold
+    }
 
     /// the main portion of our emulated interpreter
     /// where we call all the individual components of the
     /// fetch, decode, execute loop.
     /// This alters the `Emu`'s state,
-    /// but should not return anything since pixel data is
-    /// accessible from outside,
-    /// and that's really all that should be reflected.
-    pub fn fetch_decode_execute_instr(&mut self) -> Result<(), EmulationError> {
+    /// and pixel data is accessible from outside,
+    /// so the only thing returned is whether the rest of the frame
+    /// should keep running instructions, see `StepResult`.
+    pub fn fetch_decode_execute_instr(&mut self) -> Result<StepResult, EmulationError> {
+        if self.halted {
+            return Ok(StepResult::Normal);
+        }
+        let pc = self.pc;
+        if pc as usize + 1 >= self.memory.len() {
+            return Err(EmulationError::VacantMemory); // ran off the end of memory
+        }
         let opcode = self.fetch_instruction();
-        self.decode_and_execute(opcode)
+        if self.history_len > 0 {
+            if self.history.len() == self.history_len {
+                self.history.pop_front();
+            }
+            self.history.push_back((pc, opcode));
+        }
+        // only drawing gets diffed, copying the screen for every instruction would be a lot
+        let before = match self.pixel_changes {
+            Some(_) if opcode >> 12 == 0xd || opcode == 0x00e0 => Some(self.pixels.clone()),
+            _ => None,
+        };
+        let result = self.decode_and_execute(opcode);
+        if let Some(before) = before {
+            self.record_pixel_changes(&before, pc);
+        }
+        self.check_watches(pc);
+        result.map(|()| self.step_result(opcode, pc))
+    }
+
+    /// works out what the `opcode` that was just run at `pc` means for the rest of the frame
+    fn step_result(&self, opcode: u16, pc: u16) -> StepResult {
+        let (instr_type, _, _, _, nn, _) = Self::extract_from_opcode(opcode);
+        match (instr_type, nn) {
+            (0xd, _) if self.quirks.display_wait => StepResult::AwaitingVBlank,
+            (0xf, 0x0a) if self.pc == pc => StepResult::AwaitingKey, // FX0A rewound PC
+            _ => StepResult::Normal,
+        }
+    }
+
+    /// runs one 60 Hz frame: up to `cycles` instructions,
+    /// stopping early if one of them says to wait for the next frame,
+    /// and one tick of the timers, before or after the instructions
+    /// depending on `quirks.timers_before_cpu`.
+    /// Returns how many instructions actually ran.
+    /// While paused (see `set_paused`) nothing happens, not even the timers or `frame_count`,
+    /// and this returns `Ok(0)` the same as a frame that ran nothing, so check `is_paused` to tell them apart
+    pub fn step_frame(&mut self, cycles: u32) -> Result<u32, EmulationError> {
+        if self.paused {
+            return Ok(0);
+        }
+        if self.quirks.timers_before_cpu {
+            self.tick_timers();
+        }
+        let mut ran = 0;
+        while ran < cycles {
+            ran += 1;
+            if self.fetch_decode_execute_instr()? != StepResult::Normal {
+                // nothing else gets to run until the next frame
+                break;
+            }
+        }
+        if !self.quirks.timers_before_cpu {
+            self.tick_timers();
+        }
+        // taps only last until the end of the frame they happened in
+        self.tapped = self.keys.clone();
+        for key in std::mem::take(&mut self.deferred_releases) {
+            self.keys[key] = false;
+        }
+        self.frame_count += 1;
+        // the hook is taken out while it runs, since it gets all of `self`
+        if let Some(mut hook) = self.post_frame_hook.take() {
+            hook(self);
+            // unless it set a new hook while running
+            self.post_frame_hook.get_or_insert(hook);
+        }
+        let (width, height) = self.dimensions();
+        if let Some(callback) = &mut self.frame_callback {
+            callback(&self.pixels, width, height);
+        }
+        Ok(ran)
+    }
+
+    /// runs instructions until the ROM halts (see `ZeroOpcodeBehavior::Halt`) or settles into
+    /// a `1NNN` that jumps to itself, the way test ROMs usually finish, or until `max_cycles` have run.
+    /// Returns how many instructions ran. Timers don't tick, since no frames go by
+    pub fn run_until_halt(&mut self, max_cycles: usize) -> Result<usize, EmulationError> {
+        let mut ran = 0;
+        while ran < max_cycles && !self.halted && !self.at_self_jump() {
+            self.fetch_decode_execute_instr()?;
+            ran += 1;
+        }
+        Ok(ran)
+    }
+
+    /// whether the instruction at PC is a jump straight back to PC
+    fn at_self_jump(&self) -> bool {
+        let pc = self.pc as usize;
+        match (self.memory.get(pc), self.memory.get(pc + 1)) {
+            (Some(upper_half), Some(lower_half)) => {
+                u16::from_be_bytes([*upper_half, *lower_half]) == 0x1000 | self.pc
+            }
+            _ => false,
+        }
+    }
+
+    /// runs whole frames of `cycles_per_frame` instructions until `frame_count` reaches `target`,
+    /// for jumping straight to a known point of a seeded run.
+    /// Does nothing if it's already there or past it, or while paused,
+    /// since paused frames never count towards `frame_count`
+    pub fn advance_to_frame(
+        &mut self,
+        target: u64,
+        cycles_per_frame: usize,
+    ) -> Result<(), EmulationError> {
+        let cycles = u32::try_from(cycles_per_frame).unwrap_or(u32::MAX);
+        while self.frame_count < target && !self.paused {
+            self.step_frame(cycles)?;
+        }
+        Ok(())
+    }
+
+    /// how many frames `step_frame` has finished since the emulator was made
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// calls `callback` with the screen at the end of every frame `step_frame` finishes,
+    /// for embedders that want every frame, like for recording video
+    pub fn set_frame_callback(&mut self, callback: FrameCallback) {
+        self.frame_callback = Some(callback);
+    }
+
+    /// calls `hook` with the emulator itself at the end of every frame `step_frame` finishes,
+    /// before the frame callback sees the screen.
+    /// Whatever it changes stays changed, which is how cheats keep a value pinned
+    /// and scripted tests poke at the machine between frames
+    pub fn set_post_frame_hook(&mut self, hook: PostFrameHook) {
+        self.post_frame_hook = Some(hook);
+    }
+
+    /// counts both timers down once
+    fn tick_timers(&mut self) {
+        self.decrement_delay();
+        self.decrement_sound();
     }
 
     /// returns the 16 bit combination of two successive bytes
     /// with relation to instructions
     fn fetch_instruction(&mut self) -> u16 {
         let upper_half = (self.memory[self.pc as usize] as u16) << 8;
-        self.pc += 1;
+        self.pc = self.pc.wrapping_add(1);
         let lower_half = self.memory[self.pc as usize] as u16;
-        self.pc += 1;
+        self.pc = self.pc.wrapping_add(1); // 64 kB of memory goes right up to the end of a u16
         upper_half + lower_half
     }
 
@@ -211,12 +990,24 @@ impl Emu {
     /// so we combine these two steps into one,
     /// altering the state depending on the operation
     fn decode_and_execute(&mut self, opcode: u16) -> Result<(), EmulationError> {
+        if !self.denied.is_empty()
+            && pattern_for(opcode).is_some_and(|pattern| self.denied.contains(&pattern))
+        {
+            return Err(EmulationError::InstructionDenied { opcode });
+        }
+        if let Some(platform) = self.platform {
+            if !variant_for(opcode).is_none_or(|variant| platform.allows(variant)) {
+                return Err(EmulationError::OutsidePlatform { opcode });
+            }
+        }
         let (instr_type, x, y, n, nn, nnn) = Self::extract_from_opcode(opcode);
         match instr_type {
             0x0 => match nnn {
                 0x0e0 => self.clear_screen(),
                 0x0ee => self.return_from_subroutine(),
-                0x000 => Err(EmulationError::VacantMemory),
+                0x0fe => self.disable_hires(),
+                0x0ff => self.enable_hires(),
+                0x000 => self.zero_opcode(),
                 _ => Err(EmulationError::UnknownInstruction),
             },
             0x1 => self.jump(nnn),
@@ -227,7 +1018,11 @@ impl Emu {
             0x2 => self.call_subroutine(nnn),
             0x3 => self.skip_if_vx_eq_nn(x, nn),
             0x4 => self.skip_if_vx_neq_nn(x, nn),
-            0x5 => self.skip_if_vx_eq_vy(x, y),
+            0x5 => match n {
+                0x0 => self.skip_if_vx_eq_vy(x, y),
+                _ if self.quirks.skip_ignores_n => self.skip_if_vx_eq_vy(x, y),
+                _ => Err(EmulationError::UnknownInstruction),
+            },
             0x9 => self.skip_if_vx_neq_vy(x, y),
             0x8 => match n {
                 0x0 => self.set_vx_to_vy(x, y),
@@ -239,6 +1034,7 @@ impl Emu {
                 0x7 => self.vx_equals_vy_minus_vx(x, y),
                 0x6 => self.shift_right_1bit(x, y),
                 0xe => self.shift_left_1bit(x, y),
+                _ if self.quirks.lenient_8xyn => Ok(()),
                 _ => Err(EmulationError::UnknownInstruction),
             },
             0xb => self.jump_with_offset(nnn),
@@ -266,17 +1062,141 @@ impl Emu {
         }
     }
 
+    /// the address `offset` bytes past where `I` points, wrapped the way addresses wrap on this machine:
+    /// at 12 bits with 4 kB of memory, at 16 bits with XO-CHIP's 64 kB.
+    /// Sizes in between wrap at the next power of two, so past the end of memory is still past the end
+    fn index_address(&self, offset: usize) -> usize {
+        (self.i as usize + offset) & (self.memory.len().next_power_of_two() - 1)
+    }
+
+    /// the byte at `address`, for instructions that read from wherever `I` points
+    fn read_memory(&self, address: usize) -> Result<u8, EmulationError> {
+        self.memory
+            .get(address)
+            .copied()
+            .ok_or(EmulationError::InvalidAddress)
+    }
+
+    /// sets the byte at `address`, for instructions that write to wherever `I` points
+    fn write_memory(&mut self, address: usize, value: u8) -> Result<(), EmulationError> {
+        let byte = self
+            .memory
+            .get_mut(address)
+            .ok_or(EmulationError::InvalidAddress)?;
+        *byte = value;
+
+        if let Some(hits) = &mut self.self_modify_hits {
+            let pc = self.pc.wrapping_sub(2); // the fetch already moved past the writer
+            if (address as u16).wrapping_sub(pc) < SELF_MODIFY_WINDOW {
+                hits.push(SelfModifyHit {
+                    address: address as u16,
+                    pc,
+                });
+            }
+        }
+        Ok(())
+    }
+
     // -------------
     // INSTRUCTIONS
     // -------------
 
+    /// # `0000`
+    /// Not a real instruction, usually means we've run off into empty memory.
+    /// What happens depends on `quirks.zero_opcode_behavior`.
+    fn zero_opcode(&mut self) -> Result<(), EmulationError> {
+        match self.quirks.zero_opcode_behavior {
+            ZeroOpcodeBehavior::Error => Err(EmulationError::VacantMemory),
+            ZeroOpcodeBehavior::Nop => Ok(()), // PC was already moved past it by the fetch
+            ZeroOpcodeBehavior::Halt => {
+                self.pc = self.pc.wrapping_sub(2); // leave PC pointing at the 0000 for anybody inspecting state
+                self.halted = true;
+                Ok(())
+            }
+        }
+    }
+
     /// # `00E0`
     /// Turns the entire screen off.
     fn clear_screen(&mut self) -> Result<(), EmulationError> {
-        self.pixels = vec![false; 64 * 32];
+        self.pixels = vec![false; self.screen_width() * self.screen_height()];
+        self.dirty = true; // the whole screen has to be repainted blank
+        Ok(())
+    }
+
+    /// # `00FE`
+    /// SUPER-CHIP: switches back to the normal 64 x 32 screen.
+    fn disable_hires(&mut self) -> Result<(), EmulationError> {
+        self.set_hires(false);
+        Ok(())
+    }
+
+    /// # `00FF`
+    /// SUPER-CHIP: switches to the 128 x 64 high resolution screen.
+    fn enable_hires(&mut self) -> Result<(), EmulationError> {
+        self.set_hires(true);
         Ok(())
     }
 
+    /// changes resolution, which clears the screen since the old pixels don't line up
+    /// with the new ones, unless `quirks.clear_on_resolution_change` is off
+    fn set_hires(&mut self, hires: bool) {
+        let (old_width, old_height) = self.dimensions();
+        self.hires = hires;
+        if self.quirks.clear_on_resolution_change {
+            self.clear_screen().unwrap_or_default();
+        } else {
+            self.rescale_screen(old_width, old_height);
+        }
+        if let Some(decay) = &mut self.decay {
+            *decay = vec![0; self.pixels.len()];
+        }
+    }
+
+    /// stretches or squashes the screen from `old_width` by `old_height` to the current resolution,
+    /// so going to hires turns each pixel into a 2 x 2 block and going back undoes it
+    fn rescale_screen(&mut self, old_width: usize, old_height: usize) {
+        let (width, height) = self.dimensions();
+        let mut pixels = vec![false; width * height];
+        for (y, row) in pixels.chunks_mut(width).enumerate() {
+            let old_y = y * old_height / height;
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = self.pixels[old_y * old_width + x * old_width / width];
+            }
+        }
+        self.pixels = pixels;
+        self.dirty = true;
+    }
+
+    /// whether the ROM has switched to the SUPER-CHIP 128 x 64 screen with `00FF`
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// width and height of the screen in the current resolution,
+    /// which is what the framebuffer is sized to and sprites wrap and clip at
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.screen_width(), self.screen_height())
+    }
+
+    /// width of the screen in the current resolution
+    pub fn screen_width(&self) -> usize {
+        if self.hires {
+            HIRES_SIZE.0
+        } else {
+            LORES_SIZE.0
+        }
+    }
+
+    /// height of the screen in the current resolution
+    pub fn screen_height(&self) -> usize {
+        if self.hires {
+            HIRES_SIZE.1
+        } else {
+            LORES_SIZE.1
+        }
+    }
+
     /// # `1NNN`
     /// Sets the program counter to `NNN`.
     fn jump(&mut self, nnn: u16) -> Result<(), EmulationError> {
@@ -293,13 +1213,19 @@ impl Emu {
 
     /// # `7XNN`
     /// Adds the value `NN` to register `VX`.
+    /// `VF` is left alone, unless `quirks.add_immediate_sets_vf` is on,
+    /// in which case it gets the carry written last like `8XY4`.
     fn add_val_to_register(&mut self, x: u16, nn: u16) -> Result<(), EmulationError> {
         let mut temp = self.variables[x as usize] as u16;
         temp += nn;
-        if temp > 255 {
+        let carry = temp > 255;
+        if carry {
             temp -= 256;
         }
         self.variables[x as usize] = temp as u8;
+        if self.quirks.add_immediate_sets_vf {
+            self.variables[0xf] = carry as u8;
+        }
         Ok(())
     }
 
@@ -323,15 +1249,31 @@ impl Emu {
     /// Returning from a subroutine by setting the program counter
     /// to whatever is popped from the stack.
     fn return_from_subroutine(&mut self) -> Result<(), EmulationError> {
-        self.pc = self.stack_pop();
+        self.pc = self.stack_pop()?;
         Ok(())
     }
 
+    /// moves the program counter past the next instruction for the skip instructions,
+    /// which is 4 bytes for an `F000 NNNN` with `quirks.skip_long_load` on
+    fn skip_instruction(&mut self) {
+        let pc = self.pc as usize;
+        let next = match (self.memory.get(pc), self.memory.get(pc + 1)) {
+            (Some(&high), Some(&low)) => u16::from_be_bytes([high, low]),
+            _ => 0,
+        };
+        let length = if self.quirks.skip_long_load && next == 0xf000 {
+            4
+        } else {
+            2
+        };
+        self.pc = self.pc.wrapping_add(length);
+    }
+
     /// # `3XNN`
     /// Skips one instruction if value in `VX` is equal to `NN`.
     fn skip_if_vx_eq_nn(&mut self, x: u16, nn: u16) -> Result<(), EmulationError> {
         if self.variables[x as usize] == nn as u8 {
-            self.pc += 2;
+            self.skip_instruction();
         }
         Ok(())
     }
@@ -340,7 +1282,7 @@ impl Emu {
     /// Skips one instruction if the value in `VX` is not equal to `NN`.
     fn skip_if_vx_neq_nn(&mut self, x: u16, nn: u16) -> Result<(), EmulationError> {
         if self.variables[x as usize] != nn as u8 {
-            self.pc += 2;
+            self.skip_instruction();
         }
         Ok(())
     }
@@ -349,7 +1291,7 @@ impl Emu {
     /// Skips one instruction if the value in `VX` is equal to the value in `VY`.
     fn skip_if_vx_eq_vy(&mut self, x: u16, y: u16) -> Result<(), EmulationError> {
         if self.variables[x as usize] == self.variables[y as usize] {
-            self.pc += 2;
+            self.skip_instruction();
         }
         Ok(())
     }
@@ -358,7 +1300,7 @@ impl Emu {
     /// Skips one instruction if the value in `VX` is not equal to the value in `VY`.
     fn skip_if_vx_neq_vy(&mut self, x: u16, y: u16) -> Result<(), EmulationError> {
         if self.variables[x as usize] != self.variables[y as usize] {
-            self.pc += 2;
+            self.skip_instruction();
         }
         Ok(())
     }
@@ -393,106 +1335,99 @@ impl Emu {
 
     /// # `8XY4`
     /// `VX` is set to the value of `VX` plus the value of `VY`, leaving `VY` unaffected.
-    /// If the result is larger than 255, the flag register `VF` is set to 1.
+    /// If the result is larger than 255, the flag register `VF` is set to 1, otherwise 0.
+    /// The flag is written last, so with `VF` as `VX` it ends up holding the flag.
     fn vx_pluseq_vy(&mut self, x: u16, y: u16) -> Result<(), EmulationError> {
-        self.variables[0xf] = 0;
-        let mut result: u32 =
-            (self.variables[x as usize] as u32) + (self.variables[y as usize] as u32);
-        if result >= 256 {
-            result -= 256;
-            self.variables[0xf] = 1;
-        }
-        self.variables[x as usize] = result as u8;
+        let (result, carried) =
+            self.variables[x as usize].overflowing_add(self.variables[y as usize]);
+        self.variables[x as usize] = result;
+        self.variables[0xf] = carried as u8;
         Ok(())
     }
 
     /// # `8XY5`
     /// `VX` is set to the value of `VX` minus the value of `VY`, leaving `VY` unaffected.
     /// If the result has underflow, `VF` is set to 0. Otherwise, `VF` is set to 1.
+    /// Like `8XY4`, the flag is written last.
     fn vx_minuseq_vy(&mut self, x: u16, y: u16) -> Result<(), EmulationError> {
-        self.variables[0xf] = 1;
-        let mut x_val = self.variables[x as usize] as i16;
-        let y_val = self.variables[y as usize] as i16;
-        x_val -= y_val;
-        if x_val < 0 {
-            x_val += 256;
-            self.variables[0xf] = 0;
-        }
-        self.variables[x as usize] = x_val as u8;
+        let (result, borrowed) =
+            self.variables[x as usize].overflowing_sub(self.variables[y as usize]);
+        self.variables[x as usize] = result;
+        self.variables[0xf] = !borrowed as u8;
         Ok(())
     }
 
     /// # `8XY7`
     /// `VX` is set to the value of `VY` minus the value of `VX`, leaving `VY` unaffected.
-    /// If the result has underflow, `VX` is set to 0. Otherwise, `VF` is set to 1.
+    /// If the result has underflow, `VF` is set to 0. Otherwise, `VF` is set to 1.
+    /// Like `8XY4`, the flag is written last.
     fn vx_equals_vy_minus_vx(&mut self, x: u16, y: u16) -> Result<(), EmulationError> {
-        self.variables[0xf] = 1;
-        let x_val = self.variables[x as usize] as i16;
-        let mut y_val = self.variables[y as usize] as i16;
-        y_val -= x_val;
-        if y_val < 0 {
-            y_val += 256;
-            self.variables[0xf] = 0;
-        }
-        self.variables[x as usize] = y_val as u8;
+        let (result, borrowed) =
+            self.variables[y as usize].overflowing_sub(self.variables[x as usize]);
+        self.variables[x as usize] = result;
+        self.variables[0xf] = !borrowed as u8;
         Ok(())
     }
 
-    /// # `8XY6`
+    /// # `8XYE`
     /// THIS INSTRUCTION IS AMBIGUOUS!
     /// Some implementations may have a different functioning,
     /// specifically setting `VX` to `VY` before shifting that value to the left
-    /// by one bit.
+    /// by one bit, which `quirks.shift_uses_vy` turns on.
     ///
     /// Shifts the value in `VX` to the left by one bit,
     /// and then sets `VF` to the bit that was shifted out.
-    fn shift_left_1bit(&mut self, x: u16, _y: u16) -> Result<(), EmulationError> {
-        // y is of course unused at the moment
-        // but we can change this implementation to follow the other behavior
-        // by only altering this function (or adding some larger-scale configuration)
-        let to_shift = self.variables[x as usize];
-        if to_shift & 0xf0 != 0 {
-            // leftmost bit is 1
-            self.variables[0xf] = 1;
-        } else {
-            self.variables[0xf] = 0;
-        }
+    fn shift_left_1bit(&mut self, x: u16, y: u16) -> Result<(), EmulationError> {
+        let to_shift = self.shift_source(x, y);
         self.variables[x as usize] = to_shift << 1;
+        // the flag comes last, so it's what ends up in VF for 8FYE
+        self.variables[0xf] = to_shift >> 7; // leftmost bit
 
         Ok(())
     }
 
-    /// # `8XYE`
+    /// # `8XY6`
     /// THIS INSTRUCTION IS AMBIGUOUS!
     /// Some implementations may have a different functioning,
     /// specifically setting `VX` to `VY` before shifting that value to the right
-    /// by one bit.
+    /// by one bit, which `quirks.shift_uses_vy` turns on.
     ///
     /// Shifts the value in `VX` to the right by one bit,
     /// and then sets `VF` to the bit that was shifted out.
-    fn shift_right_1bit(&mut self, x: u16, _y: u16) -> Result<(), EmulationError> {
+    fn shift_right_1bit(&mut self, x: u16, y: u16) -> Result<(), EmulationError> {
         // same situation as Emu.shift_left_1bit
-        let to_shift = self.variables[x as usize];
-        if to_shift & 0x1 != 0 {
-            // rightmost bit is 1
-            self.variables[0xf] = 1;
-        } else {
-            self.variables[0xf] = 0;
-        }
+        let to_shift = self.shift_source(x, y);
         self.variables[x as usize] = to_shift >> 1;
+        self.variables[0xf] = to_shift & 0x1; // rightmost bit
 
         Ok(())
     }
 
+    /// the value `8XY6` and `8XYE` shift, which is also where the bit in `VF` comes from
+    fn shift_source(&self, x: u16, y: u16) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.variables[y as usize]
+        } else {
+            self.variables[x as usize]
+        }
+    }
+
     /// # `BNNN`
     /// THIS INSTRUCTION IS AMBIGUOUS!
     /// Some implementations may have a different functioning,
     /// basically working as an alternate `BXNN`.
     ///
     /// Program counter jumps to the value of
-    /// `NNN` plus the value stored in `V0`.
+    /// `NNN` plus the value stored in `V0`,
+    /// or with `quirks.jump_uses_vx`, `XNN` plus the value stored in `VX`.
+    /// Either way the address wraps around to stay within 12 bits.
     fn jump_with_offset(&mut self, nnn: u16) -> Result<(), EmulationError> {
-        self.pc = nnn + (self.variables[0x0] as u16);
+        let register = if self.quirks.jump_uses_vx {
+            (nnn >> 8) as usize // X is the top nibble of NNN
+        } else {
+            0x0
+        };
+        self.pc = nnn.wrapping_add(self.variables[register] as u16) & 0xfff;
         Ok(())
     }
 
@@ -500,19 +1435,36 @@ impl Emu {
     /// Generates a random number, binary ANDs with value `NN`,
     /// and puts that result in `VX`.
     fn random_gen(&mut self, x: u16, nn: u16) -> Result<(), EmulationError> {
-        let mut rng = rand::thread_rng();
-        let generated: u8 = rng.gen();
+        if self.lint && nn == 0 && !self.warned_zero_mask {
+            self.warned_zero_mask = true;
+            self.warnings.push(format!(
+                "CXNN at 0x{:03X} masks with 0x00, so it always gives 0",
+                self.pc.wrapping_sub(2)
+            ));
+        }
+        let generated = self.rng.next_byte();
         self.variables[x as usize] = generated & (nn as u8);
         Ok(())
     }
 
+    /// the key `EX9E` and `EXA1` look at, the one in `VX`.
+    /// There are only 16 keys, so larger values are masked to their
+    /// lowest hex digit, or are an error with `quirks.strict_keys`.
+    fn key_in(&self, x: u16) -> Result<usize, EmulationError> {
+        let key = self.variables[x as usize];
+        if key > 0xf && self.quirks.strict_keys {
+            return Err(EmulationError::InvalidRegister);
+        }
+        Ok((key & 0xf) as usize)
+    }
+
     /// # `EX9E`
     /// Program counter skips one instruction if
     /// the represented key in `VX` is pressed.
     fn skip_if_key(&mut self, x: u16) -> Result<(), EmulationError> {
-        let key_pos = self.variables[x as usize] as usize;
-        if self.keys[key_pos] {
-            self.pc += 2;
+        let key_pos = self.key_in(x)?;
+        if self.is_key_down(key_pos) {
+            self.skip_instruction();
         }
 
         Ok(())
@@ -522,9 +1474,9 @@ impl Emu {
     /// Program counter skips one instruction if
     /// the represented key in `VX` is not pressed.
     fn skip_if_not_key(&mut self, x: u16) -> Result<(), EmulationError> {
-        let key_pos = self.variables[x as usize] as usize;
-        if !self.keys[key_pos] {
-            self.pc += 2;
+        let key_pos = self.key_in(x)?;
+        if !self.is_key_down(key_pos) {
+            self.skip_instruction();
         }
 
         Ok(())
@@ -553,9 +1505,9 @@ impl Emu {
 
     /// # `FX1E`
     /// Index register is changed to the sum of itself
-    /// with the value in `VX`
+    /// with the value in `VX`, wrapping around like every other address, see `index_address`
     fn add_to_index(&mut self, x: u16) -> Result<(), EmulationError> {
-        self.i += self.variables[x as usize] as u16;
+        self.i = self.index_address(self.variables[x as usize] as usize) as u16;
         Ok(())
     }
 
@@ -568,19 +1520,25 @@ impl Emu {
     /// and execution continues.
     fn get_key(&mut self, x: u16) -> Result<(), EmulationError> {
         for key_pos in 0..self.keys.len() {
-            if self.keys[key_pos] {
+            if self.is_key_down(key_pos) {
                 self.variables[x as usize] = key_pos as u8;
                 return Ok(());
             }
         }
-        self.pc -= 2;
+        self.pc = self.pc.wrapping_sub(2);
         Ok(())
     }
 
     /// # `FX29`
     /// Index register is set to the address of the hexadecimal character in `VX`.
+    /// There are only glyphs for `0` through `F`, so larger values are masked to their
+    /// lowest hex digit, or are an error with `quirks.strict_font`.
     fn font_character(&mut self, x: u16) -> Result<(), EmulationError> {
-        self.i = self.variables[x as usize] as u16;
+        let character = self.variables[x as usize];
+        if character > 0xf && self.quirks.strict_font {
+            return Err(EmulationError::InvalidRegister);
+        }
+        self.i = (character & 0xf) as u16;
         self.i *= 5; // each char starts at an offset of 5 after font memory position
         self.i += 0x050; // start of font index in memory
         Ok(())
@@ -597,9 +1555,9 @@ impl Emu {
         let tens = ((val % 100) - ones) / 10;
         let hundreds = ((val % 1000) - ones - tens) / 100;
 
-        self.memory[self.i as usize] = hundreds as u8;
-        self.memory[(self.i + 1) as usize] = tens as u8;
-        self.memory[(self.i + 2) as usize] = ones as u8;
+        self.write_memory(self.index_address(0), hundreds as u8)?;
+        self.write_memory(self.index_address(1), tens as u8)?;
+        self.write_memory(self.index_address(2), ones as u8)?;
 
         Ok(())
     }
@@ -607,10 +1565,15 @@ impl Emu {
     /// # `FX55`
     /// Value of each variable register from `V0` to `VX` inclusive is stored in successive memory addresses,
     /// starting with the address that the index register is currently pointing at.
+    ///
+    /// THIS INSTRUCTION IS AMBIGUOUS!
+    /// Interpreters disagree on where the index register ends up afterwards,
+    /// see `LoadStoreBehavior`.
     fn store_memory(&mut self, x: u16) -> Result<(), EmulationError> {
-        for var in 0..x + 1 {
-            self.memory[(self.i + var) as usize] = self.variables[var as usize];
+        for var in 0..x as usize + 1 {
+            self.write_memory(self.index_address(var), self.variables[var])?;
         }
+        self.advance_index_after_load_store(x);
 
         Ok(())
     }
@@ -618,13 +1581,25 @@ impl Emu {
     /// # `FX65`
     /// Does the same thing as `store_memory`, but loads them into the variable registers instead.`
     fn load_memory(&mut self, x: u16) -> Result<(), EmulationError> {
-        for var in 0..x + 1 {
-            self.variables[var as usize] = self.memory[(self.i + var) as usize];
+        for var in 0..x as usize + 1 {
+            self.variables[var] = self.read_memory(self.index_address(var))?;
         }
+        self.advance_index_after_load_store(x);
 
         Ok(())
     }
 
+    /// moves the index register after `FX55` or `FX65` according to `quirks.load_store`.
+    /// The COSMAC VIP walks I forward once per register, so it ends one past `VX`'s address,
+    /// while CHIP-48 has an off by one and ends at `VX`'s address.
+    fn advance_index_after_load_store(&mut self, x: u16) {
+        match self.quirks.load_store {
+            LoadStoreBehavior::Unchanged => (),
+            LoadStoreBehavior::Cosmac => self.i = self.index_address(x as usize + 1) as u16,
+            LoadStoreBehavior::Hp48 => self.i = self.index_address(x as usize) as u16,
+        }
+    }
+
     /// # `DXYN`
     /// Draws an `N` pixels tall sprite from memory location
     /// that the index register is currently pointing at,
@@ -633,68 +1608,635 @@ impl Emu {
     ///
     /// If any pixels on the screen were turned "off" by doing this,
     /// `VF` register is set to 1. Otherwise, it's set to 0.
+    ///
+    /// # `DXY0`
+    /// SUPER-CHIP: with `N` of 0, draws a 16 x 16 sprite instead,
+    /// made of 32 bytes with two bytes per row.
     fn display(&mut self, x: u16, y: u16, n: u16) -> Result<(), EmulationError> {
+        self.drew_anything = true;
+        let width = self.screen_width();
+        let height = self.screen_height();
         // starting position wraps, so we can do the same as
-        // binary anding (or modulo) the display
+        // binary anding (or modulo) the display, with the mask following the resolution
         // the actual drawing of the sprite does not wrap however
-        let mut x = (self.variables[x as usize] & 63) as usize;
-        let mut y = (self.variables[y as usize] & 31) as usize;
-        self.variables[0xf] = 0;
-
-        //(x + y * 64) as usize
-
-        for byte_index in 0..n {
-            let mut sprite_byte = self.memory[(self.i + byte_index) as usize];
-            if y == 31 {
+        let (x, mut y) = (
+            self.variables[x as usize] as usize,
+            self.variables[y as usize] as usize,
+        );
+        let x = if self.quirks.clamp_sprite_start {
+            y = y.min(height - 1);
+            x.min(width - 1)
+        } else {
+            y &= height - 1;
+            x & (width - 1)
+        };
+        let (rows, sprite_width) = if n == 0 { (16, 16) } else { (n, 8) };
+        let mut collided_rows = 0;
+        let mut clipped_rows = 0;
+
+        for row in 0..rows {
+            if y == height {
+                // ran off the bottom of the screen
+                clipped_rows = (rows - row) as u8;
                 break;
             }
+            // each row is lined up to the left of 16 bits, whichever width it is
+            let row_bits = if sprite_width == 16 {
+                let address = row as usize * 2;
+                u16::from_be_bytes([
+                    self.read_memory(self.index_address(address))?,
+                    self.read_memory(self.index_address(address + 1))?,
+                ])
+            } else {
+                (self.read_memory(self.index_address(row as usize))? as u16) << 8
+            };
 
             // for each bit in this sprite row...
-            for i in 0..8 {
-                if sprite_byte & 0x80 != 0 {
-                    // leftmost bit is "turned on", 2^i
-                    if self.pixels[(x + y * 64) as usize] {
-                        self.pixels[(x + y * 64) as usize] = false;
-                        self.variables[0xf] = 1;
+            let mut collided = false;
+            for column in 0..sprite_width {
+                if x + column == width {
+                    // ran off the right of the screen
+                    break;
+                }
+                if row_bits & (0x8000 >> column) != 0 {
+                    // this bit is "turned on"
+                    let pixel_index = x + column + y * width;
+                    self.dirty = true;
+                    if self.draw_mode == DrawMode::Or {
+                        self.pixels[pixel_index] = true;
+                    } else if self.pixels[pixel_index] {
+                        self.pixels[pixel_index] = false;
+                        collided = true;
                     } else {
-                        self.pixels[(x + y * 64) as usize] = true;
+                        self.pixels[pixel_index] = true;
                     }
                 }
-                x += 1;
-                if x == 63 || i == 7 {
-                    x -= i + 1;
-                    y += 1;
-                    break;
-                }
-                sprite_byte <<= 1;
             }
+            collided_rows += collided as u8;
+            y += 1;
         }
 
+        self.variables[0xf] = if self.draw_mode == DrawMode::Or {
+            0 // nothing ever gets turned off, so there's never a collision
+        } else if self.hires && self.quirks.clip_counter {
+            collided_rows + clipped_rows
+        } else if self.hires && self.quirks.count_collided_rows {
+            collided_rows
+        } else {
+            (collided_rows > 0) as u8
+        };
         Ok(())
     }
 
+    /// reseeds the random number generator used by `CXNN`,
+    /// so that a run can be replayed exactly.
+    /// Without this, it's seeded from entropy.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Box::new(StdRng::seed_from_u64(seed));
+    }
+    /// replaces the random number generator used by `CXNN` altogether
+    pub fn set_random_source(&mut self, source: Box<dyn RandomSource>) {
+        self.rng = source;
+    }
+
     // -----------
     // KEYPRESSES
     // -----------
     /// tells the emulator that key at `key_index` was pressed
     pub fn keypress(&mut self, key_index: usize) {
+        if self.paused {
+            self.paused_keys.push((key_index, true));
+            return;
+        }
         self.keys[key_index] = true;
+        self.tapped[key_index] = true;
     }
     /// tells the emulator that key at `key_index` was released
     pub fn keyrelease(&mut self, key_index: usize) {
+        if self.paused {
+            self.paused_keys.push((key_index, false));
+            return;
+        }
         self.keys[key_index] = false;
     }
-}
 
-// these tests are kind of sparse since we have a few ROMs that test for us
+    /// lets go of every key straight away, even while paused, and forgets about
+    /// presses and releases waiting on the pause to end, so nothing carries over into a new ROM.
+    /// Loading a ROM does this too, leaving just `EmuBuilder::initial_keys` held
+    pub fn clear_keys(&mut self) {
+        self.keys.fill(false);
+        self.tapped.fill(false);
+        self.paused_keys.clear();
+        self.deferred_releases.clear();
+    }
 
-#[test]
-fn test_instruction_fetch() {
-    // tests on the first two bytes of font data
-    // that the return is correct
-    let mut emulator = Emu::new();
-    emulator.pc = 0x050;
-    assert_eq!(emulator.fetch_instruction(), 0xF090);
+    /// clears the keys, then holds down the ones a ROM starts with
+    fn hold_initial_keys(&mut self) {
+        self.clear_keys();
+        for key in 0..self.keys.len() {
+            if self.initial_keys & (1 << key) != 0 {
+                self.keys[key] = true;
+                self.tapped[key] = true;
+            }
+        }
+    }
+
+    /// lets go of every key, for when the frontend stops hearing about key releases,
+    /// like when its window loses focus
+    pub fn release_all_keys(&mut self) {
+        for key in 0..self.keys.len() {
+            self.keyrelease(key);
+        }
+    }
+
+    /// pauses or resumes the machine. While paused `step_frame` does nothing,
+    /// and keys pressed and released are held on to instead of taking effect.
+    /// Resuming replays them, with any key that was pressed during the pause
+    /// staying down for the first frame, so a tap while paused still gets through to `FX0A`
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if paused {
+            return;
+        }
+        let mut pressed = vec![];
+        for (key, down) in std::mem::take(&mut self.paused_keys) {
+            if down {
+                self.keypress(key);
+                pressed.push(key);
+                // pressed again after a tap, so it's held after all
+                self.deferred_releases.retain(|deferred| *deferred != key);
+            } else if pressed.contains(&key) {
+                self.deferred_releases.push(key);
+            } else {
+                self.keyrelease(key);
+            }
+        }
+    }
+    /// true while paused with `set_paused`
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// whether the key at `key_index` counts as down for the instructions that check keys.
+    /// With `quirks.latch_key_taps`, a key pressed at any point this frame counts,
+    /// so a tap shorter than a frame isn't missed.
+    fn is_key_down(&self, key_index: usize) -> bool {
+        self.keys[key_index] || (self.quirks.latch_key_taps && self.tapped[key_index])
+    }
+    /// returns the index of every key that's currently held down, in order
+    pub fn held_keys(&self) -> Vec<u8> {
+        (0..16u8).filter(|key| self.keys[*key as usize]).collect()
+    }
+
+    // ------------
+    // FRAMEBUFFER
+    // ------------
+    /// returns the screen's pixels row by row, `true` if on.
+    /// This used to be the public `pixels` field, which moved behind this
+    /// so the emulator can keep track of changes to the screen. Taking the field away was
+    /// a deliberate API change, and this stays around for code that read it, but new code
+    /// should use `framebuffer_clone` or `framebuffer_packed` instead
+    #[deprecated(note = "use `framebuffer_clone` or `framebuffer_packed` instead")]
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    /// how many pixels on the screen are lit
+    pub fn lit_pixel_count(&self) -> usize {
+        self.pixels.iter().filter(|lit| **lit).count()
+    }
+
+    /// returns the screen packed into bytes, one bit per pixel, row by row.
+    /// Within each byte the leftmost pixel is the most significant bit,
+    /// the same layout sprites use, so each row of the screen is `width / 8` bytes
+    /// (8 in lores, 16 in hires) that could be drawn back with `DXYN`.
+    pub fn framebuffer_packed(&self) -> Vec<u8> {
+        self.pixels
+            .chunks(8)
+            .map(|pixels| {
+                pixels
+                    .iter()
+                    .fold(0, |byte, pixel| (byte << 1) | (*pixel as u8))
+            })
+            .collect()
+    }
+    /// whether the screen changed since the last time this was called,
+    /// so a frontend can skip repainting when nothing happened.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// draws the screen as text, `#` for lit pixels and `.` for unlit ones,
+    /// with a newline after every row, so tests can compare against a picture
+    pub fn framebuffer_ascii(&self) -> String {
+        self.pixels
+            .chunks(self.screen_width())
+            .flat_map(|row| {
+                row.iter()
+                    .map(|lit| if *lit { '#' } else { '.' })
+                    .chain(std::iter::once('\n'))
+            })
+            .collect()
+    }
+
+    /// 64-bit FNV-1a hash of `framebuffer_packed`,
+    /// so tests can compare a whole screen against one number
+    pub fn framebuffer_hash(&self) -> u64 {
+        self.framebuffer_packed()
+            .iter()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+    /// restores a screen from the layout `framebuffer_packed` gives
+    pub fn load_framebuffer_packed(&mut self, bytes: &[u8]) -> Result<(), EmulationError> {
+        if bytes.len() * 8 != self.pixels.len() {
+            return Err(EmulationError::FramebufferSize);
+        }
+        self.dirty = true;
+        for (pixels, byte) in self.pixels.chunks_mut(8).zip(bytes) {
+            for (bit, pixel) in pixels.iter_mut().enumerate() {
+                *pixel = byte & (0x80 >> bit) != 0;
+            }
+        }
+        Ok(())
+    }
+    /// a copy of the screen, for putting back later with `restore_framebuffer`
+    pub fn framebuffer_clone(&self) -> Vec<bool> {
+        self.pixels.clone()
+    }
+    /// puts back a screen from `framebuffer_clone`,
+    /// as long as it's the size of the screen in the current resolution
+    pub fn restore_framebuffer(&mut self, framebuffer: &[bool]) -> Result<(), EmulationError> {
+        if framebuffer.len() != self.pixels.len() {
+            return Err(EmulationError::FramebufferSize);
+        }
+        self.pixels.copy_from_slice(framebuffer);
+        self.dirty = true;
+        Ok(())
+    }
+
+    // --------------
+    // PHOSPHOR DECAY
+    // --------------
+    /// turns on phosphor decay, where pixels fade out over `frames` frames
+    /// after turning off instead of vanishing right away,
+    /// like on an old CRT. `pixels` itself is left alone.
+    pub fn enable_decay(&mut self, frames: u8) {
+        // rounded up so pixels reach 0 in time
+        self.decay_step = 255u8.div_ceil(frames.max(1));
+        self.decay = Some(
+            self.pixels
+                .iter()
+                .map(|lit| if *lit { 255 } else { 0 })
+                .collect(),
+        );
+    }
+    /// turns phosphor decay back off
+    pub fn disable_decay(&mut self) {
+        self.decay = None;
+    }
+    /// advances the decay by one frame, so should be called once per frame.
+    /// Lit pixels are at full brightness, and unlit ones fade towards 0.
+    pub fn tick_decay(&mut self) {
+        if let Some(decay) = &mut self.decay {
+            for (brightness, lit) in decay.iter_mut().zip(&self.pixels) {
+                *brightness = if *lit {
+                    255
+                } else {
+                    brightness.saturating_sub(self.decay_step)
+                };
+            }
+        }
+    }
+    /// brightness of each pixel from 0 (off) to 255 (lit),
+    /// or `None` if phosphor decay isn't turned on
+    pub fn decay(&self) -> Option<&[u8]> {
+        self.decay.as_deref()
+    }
+
+    // ----------
+    // DEBUGGING
+    // ----------
+    /// true if `frames` frames have gone by without a single `DXYN`,
+    /// which usually means the ROM is for another variant or wants different quirks
+    pub fn never_drew_after(&self, frames: u64) -> bool {
+        !self.drew_anything && self.frame_count >= frames
+    }
+    /// true once the ROM has halted, see `ZeroOpcodeBehavior::Halt`
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// compares the state of two emulators, returning a description of each difference.
+    /// Memory isn't compared byte by byte, but the number of lit pixels is.
+    /// Meant to be printed when a test's assertion fails.
+    pub fn diff(&self, other: &Emu) -> Vec<String> {
+        let mut differences = vec![];
+        if self.pc != other.pc {
+            differences.push(format!("PC: 0x{:03X} != 0x{:03X}", self.pc, other.pc));
+        }
+        if self.i != other.i {
+            differences.push(format!("I: 0x{:03X} != 0x{:03X}", self.i, other.i));
+        }
+        for (register, (mine, theirs)) in self.variables.iter().zip(&other.variables).enumerate() {
+            if mine != theirs {
+                differences.push(format!(
+                    "V{:X}: 0x{:02X} != 0x{:02X}",
+                    register, mine, theirs
+                ));
+            }
+        }
+        if self.delay_timer != other.delay_timer {
+            differences.push(format!(
+                "delay timer: {} != {}",
+                self.delay_timer, other.delay_timer
+            ));
+        }
+        if self.sound_timer != other.sound_timer {
+            differences.push(format!(
+                "sound timer: {} != {}",
+                self.sound_timer, other.sound_timer
+            ));
+        }
+        if self.the_stack != other.the_stack {
+            differences.push(format!(
+                "stack: {:X?} != {:X?}",
+                self.the_stack, other.the_stack
+            ));
+        }
+        let lit = self.pixels.iter().filter(|pixel| **pixel).count();
+        let other_lit = other.pixels.iter().filter(|pixel| **pixel).count();
+        if lit != other_lit {
+            differences.push(format!(
+                "lit pixels: {} != {} ({:+})",
+                lit,
+                other_lit,
+                other_lit as i64 - lit as i64
+            ));
+        }
+        differences
+    }
+
+    /// stops every opcode matching `pattern` (like `FX55` or `CXNN`) from running,
+    /// so they return `EmulationError::InstructionDenied` instead.
+    /// Patterns are the ones from `supported_opcodes`, and a more specific one
+    /// counts as its own family, so denying `DXYN` still leaves `DXY0` alone.
+    /// Returns false if `pattern` isn't one of them
+    pub fn deny(&mut self, pattern: &str) -> bool {
+        let pattern = pattern.to_ascii_uppercase();
+        match supported_opcodes()
+            .iter()
+            .find(|info| info.pattern == pattern)
+        {
+            Some(info) => {
+                self.denied.push(info.pattern);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// starts watching `target`, so that any instruction changing its value
+    /// gets recorded as a `WatchHit`
+    pub fn add_watch(&mut self, target: WatchTarget) {
+        let value = self.watched_value(target);
+        self.watches.push((target, value));
+    }
+    /// returns every watched change since the last time this was called, oldest first
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.watch_hits)
+    }
+    /// starts or stops recording a `SelfModifyHit` whenever an instruction writes to itself
+    /// or to the few instructions after it, which usually means self-modifying code
+    pub fn detect_self_modifying(&mut self, detect: bool) {
+        self.self_modify_hits = if detect { Some(vec![]) } else { None };
+    }
+    /// switches how sprites get drawn. `DrawMode::Or` keeps every sprite pixel lit,
+    /// so sprite tables can be seen clearly without anything flickering off
+    pub fn set_draw_mode(&mut self, mode: DrawMode) {
+        self.draw_mode = mode;
+    }
+    /// starts keeping the address and opcode of the last `len` instructions run,
+    /// for working out what led up to an error. 0 stops keeping them
+    pub fn keep_history(&mut self, len: usize) {
+        self.history_len = len;
+        while self.history.len() > len {
+            self.history.pop_front();
+        }
+    }
+    /// the `(address, opcode)` of the last instructions run, oldest first, see `keep_history`.
+    /// An instruction that failed is included as the last one
+    pub fn recent_history(&self) -> Vec<(u16, u16)> {
+        self.history.iter().copied().collect()
+    }
+    /// starts or stops recording a `PixelChange` for every pixel a `DXYN` or `00E0` flips,
+    /// for tracking down where a sprite really ended up. Expect a lot of them
+    pub fn log_pixel_changes(&mut self, log: bool) {
+        self.pixel_changes = if log { Some(vec![]) } else { None };
+    }
+    /// starts or stops warning about things ROMs probably didn't mean to do, for ROM authors.
+    /// So far that's a `CXNN` with `NN` of 0, which always gives 0, warned about once per ROM
+    pub fn lint(&mut self, lint: bool) {
+        self.lint = lint;
+    }
+    /// returns the warnings from `lint` since the last time this was called, oldest first
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+    /// returns every pixel drawing changed since the last time this was called, oldest first
+    pub fn take_pixel_changes(&mut self) -> Vec<PixelChange> {
+        self.pixel_changes
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+    fn record_pixel_changes(&mut self, before: &[bool], pc: u16) {
+        let width = self.screen_width();
+        if let Some(changes) = &mut self.pixel_changes {
+            let changed = before.iter().zip(&self.pixels).enumerate();
+            for (index, (_, new)) in changed.filter(|(_, (old, new))| old != new) {
+                changes.push(PixelChange {
+                    x: index % width,
+                    y: index / width,
+                    lit: *new,
+                    pc,
+                });
+            }
+        }
+    }
+    /// returns every write into upcoming code since the last time this was called, oldest first
+    pub fn take_self_modify_hits(&mut self) -> Vec<SelfModifyHit> {
+        self.self_modify_hits
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+    fn watched_value(&self, target: WatchTarget) -> u8 {
+        match target {
+            WatchTarget::Register(register) => self.variables[(register & 0xf) as usize],
+            WatchTarget::Memory(address) => self.memory[address as usize % self.memory.len()],
+        }
+    }
+    /// records a `WatchHit` for every watched value that changed
+    /// since the instruction at `pc` ran
+    fn check_watches(&mut self, pc: u16) {
+        for index in 0..self.watches.len() {
+            let (target, old) = self.watches[index];
+            let new = self.watched_value(target);
+            if new != old {
+                self.watches[index].1 = new;
+                self.watch_hits.push(WatchHit {
+                    target,
+                    pc,
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+
+    /// address of the next instruction to run
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// how many subroutine calls deep the ROM is, the number of return addresses on the stack
+    pub fn stack_depth(&self) -> usize {
+        self.the_stack.len()
+    }
+
+    /// what the delay timer is at
+    pub fn delay(&self) -> u8 {
+        self.delay_timer
+    }
+
+    /// what the sound timer is at
+    pub fn sound(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// a copy of `V0` through `VF`, for comparing the whole register file at once
+    pub fn register_dump(&self) -> [u8; 16] {
+        let mut registers = [0; 16];
+        registers.copy_from_slice(&self.variables);
+        registers
+    }
+
+    /// marks `address` as somewhere a debugger should stop
+    pub fn add_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.contains(&address) {
+            self.breakpoints.push(address);
+        }
+    }
+    /// unmarks `address`, returning false if it wasn't a breakpoint
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| *breakpoint != address);
+        self.breakpoints.len() != count
+    }
+    /// true if the program counter is sitting on a breakpoint
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.pc)
+    }
+
+    /// returns up to `len` bytes of memory starting at `start`,
+    /// cut short if that would go past the end of memory
+    pub fn memory_range(&self, start: u16, len: usize) -> &[u8] {
+        let start = (start as usize).min(self.memory.len());
+        let end = start.saturating_add(len).min(self.memory.len());
+        &self.memory[start..end]
+    }
+
+    /// disassembles `count` instructions starting at `start`, one line each,
+    /// like `0x200  6010  LD V0, 0x10`.
+    /// Anything that doesn't decode (sprite data, usually) is shown as `???`.
+    pub fn disassemble(&self, start: u16, count: usize) -> Vec<String> {
+        self.memory_range(start, count * 2)
+            .chunks_exact(2)
+            .zip((start..).step_by(2))
+            .map(|(bytes, address)| {
+                let opcode = ((bytes[0] as u16) << 8) + bytes[1] as u16;
+                match decode(opcode) {
+                    Ok(instruction) => {
+                        format!("0x{:03X}  {:04X}  {}", address, opcode, instruction)
+                    }
+                    Err(_) => format!("0x{:03X}  {:04X}  ???", address, opcode),
+                }
+            })
+            .collect()
+    }
+
+    /// the same as `disassemble`, but as one listing with a comment after each instruction
+    /// saying what it does, for reading along with a ROM while learning how it works
+    pub fn disassemble_annotated(&self, start: u16, count: usize) -> String {
+        let mut listing = String::new();
+        for (bytes, address) in self
+            .memory_range(start, count * 2)
+            .chunks_exact(2)
+            .zip((start..).step_by(2))
+        {
+            let opcode = ((bytes[0] as u16) << 8) + bytes[1] as u16;
+            let (mnemonic, comment) = match decode(opcode) {
+                Ok(instruction) => (instruction.to_string(), instruction.describe()),
+                Err(_) => (
+                    "???".to_string(),
+                    "not an instruction, maybe data".to_string(),
+                ),
+            };
+            writeln!(
+                listing,
+                "0x{:03X}  {:04X}  {:<18} ; {}",
+                address, opcode, mnemonic, comment
+            )
+            .unwrap();
+        }
+        listing
+    }
+
+    /// decodes the instruction at the program counter without running it
+    /// or changing any state, so a frontend can show what's coming up next
+    pub fn peek_instruction(&self) -> Result<Instruction, EmulationError> {
+        let pc = self.pc as usize;
+        match (self.memory.get(pc), self.memory.get(pc + 1)) {
+            (Some(upper_half), Some(lower_half)) => {
+                decode(((*upper_half as u16) << 8) + *lower_half as u16)
+            }
+            _ => Err(EmulationError::VacantMemory), // ran off the end of memory
+        }
+    }
+
+    /// returns a one line summary of the CPU state, something like
+    /// `PC=0x24A I=0x300 SP=2 DT=5 ST=0 V0=01 V1=FF ...`.
+    /// Handy for printing state from a frontend or a trace log.
+    pub fn status_line(&self) -> String {
+        // 39 bytes for the header plus 6 bytes for each of the 16 registers
+        let mut line = String::with_capacity(39 + 16 * 6);
+        // writing to a String can't fail, so the results are ignored
+        let _ = write!(
+            line,
+            "PC=0x{:03X} I=0x{:03X} SP={} DT={} ST={}",
+            self.pc,
+            self.i,
+            self.the_stack.len(),
+            self.delay_timer,
+            self.sound_timer
+        );
+        for (register, value) in self.variables.iter().enumerate() {
+            let _ = write!(line, " V{:X}={:02X}", register, value);
+        }
+        line
+    }
+}
+
+// these tests are kind of sparse since we have a few ROMs that test for us
+
+#[test]
+fn test_instruction_fetch() {
+    // tests on the first two bytes of font data
+    // that the return is correct
+    let mut emulator = Emu::new();
+    emulator.pc = 0x050;
+    assert_eq!(emulator.fetch_instruction(), 0xF090);
 }
 
 #[test]
@@ -717,3 +2259,1769 @@ fn test_opcode_extraction() {
     assert_eq!(nibbles_34, nn);
     assert_eq!(nibbles_234, nnn);
 }
+
+#[test]
+fn test_status_line() {
+    let mut emulator = Emu::new();
+    emulator.pc = 0x24a;
+    emulator.i = 0x300;
+    emulator.the_stack = vec![0x200, 0x220];
+    emulator.delay_timer = 5;
+    emulator.variables[0x0] = 0x01;
+    emulator.variables[0x1] = 0xff;
+    emulator.variables[0xf] = 0x1;
+    assert_eq!(
+        emulator.status_line(),
+        "PC=0x24A I=0x300 SP=2 DT=5 ST=0 \
+         V0=01 V1=FF V2=00 V3=00 V4=00 V5=00 V6=00 V7=00 \
+         V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=01"
+    );
+}
+
+#[test]
+fn test_zero_opcode_behavior() {
+    // sets V0, hits a 0000, then would set V1
+    let rom = vec![0x60, 0x01, 0x00, 0x00, 0x61, 0x02];
+
+    let mut emulator = Emu::new();
+    emulator.read_rom(rom.clone()).unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(matches!(
+        emulator.fetch_decode_execute_instr(),
+        Err(EmulationError::VacantMemory)
+    ));
+
+    let mut emulator = Emu::new();
+    emulator.quirks.zero_opcode_behavior = ZeroOpcodeBehavior::Nop;
+    emulator.read_rom(rom.clone()).unwrap();
+    for _ in 0..3 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+    assert_eq!(emulator.variables[0x1], 0x02);
+    assert_eq!(emulator.pc, 0x206);
+
+    let mut emulator = Emu::new();
+    emulator.quirks.zero_opcode_behavior = ZeroOpcodeBehavior::Halt;
+    emulator.read_rom(rom).unwrap();
+    for _ in 0..3 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+    assert!(emulator.is_halted());
+    assert_eq!(emulator.variables[0x1], 0x00);
+    assert_eq!(emulator.pc, 0x202);
+}
+
+#[test]
+fn test_instruction_fetch_is_big_endian() {
+    // the first byte is the high half of the opcode
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x12, 0x34, 0xab, 0xcd]).unwrap();
+    assert_eq!(emulator.fetch_instruction(), 0x1234);
+    assert_eq!(emulator.pc, 0x202);
+    assert_eq!(emulator.fetch_instruction(), 0xabcd);
+    assert_eq!(emulator.pc, 0x204);
+}
+
+#[test]
+fn test_instruction_fetch_at_memory_end() {
+    // the last two bytes of memory are still a valid instruction
+    let mut emulator = Emu::new();
+    emulator.memory[0xffe] = 0x00;
+    emulator.memory[0xfff] = 0xe0;
+    emulator.pc = 0xffe;
+    assert_eq!(emulator.fetch_instruction(), 0x00e0);
+    assert_eq!(emulator.pc, 0x1000);
+}
+
+#[test]
+fn test_held_keys() {
+    let mut emulator = Emu::new();
+    assert!(emulator.held_keys().is_empty());
+    emulator.keypress(0xa);
+    emulator.keypress(0x1);
+    assert_eq!(emulator.held_keys(), vec![0x1, 0xa]);
+    emulator.keyrelease(0xa);
+    assert_eq!(emulator.held_keys(), vec![0x1]);
+}
+
+#[test]
+fn test_font_character_out_of_range() {
+    // FX29 with V0 = 0x1A
+    let mut emulator = Emu::new();
+    emulator.variables[0x0] = 0x1a;
+    emulator.decode_and_execute(0xf029).unwrap();
+    assert_eq!(emulator.i, 0x050 + 0xa * 5); // masked down to the A glyph
+
+    let mut emulator = Emu::new();
+    emulator.quirks.strict_font = true;
+    emulator.variables[0x0] = 0x1a;
+    assert!(matches!(
+        emulator.decode_and_execute(0xf029),
+        Err(EmulationError::InvalidRegister)
+    ));
+}
+
+#[test]
+fn test_key_out_of_range() {
+    // EX9E and EXA1 with V0 = 0x1F and key F held
+    let mut emulator = Emu::new();
+    emulator.variables[0x0] = 0x1f;
+    emulator.keypress(0xf);
+    emulator.decode_and_execute(0xe09e).unwrap();
+    assert_eq!(emulator.pc, 0x202); // masked down to key F, which is down
+    emulator.decode_and_execute(0xe0a1).unwrap();
+    assert_eq!(emulator.pc, 0x202);
+
+    let mut emulator = Emu::new();
+    emulator.quirks.strict_keys = true;
+    emulator.variables[0x0] = 0x1f;
+    emulator.keypress(0xf);
+    for opcode in [0xe09e, 0xe0a1] {
+        assert!(matches!(
+            emulator.decode_and_execute(opcode),
+            Err(EmulationError::InvalidRegister)
+        ));
+    }
+    assert_eq!(emulator.pc, 0x200);
+}
+
+#[test]
+fn test_diff() {
+    let emulator = Emu::new();
+    let mut other = Emu::new();
+    assert!(emulator.diff(&other).is_empty());
+
+    other.variables[0x3] = 0x42;
+    assert_eq!(emulator.diff(&other), vec!["V3: 0x00 != 0x42"]);
+}
+
+#[test]
+fn test_is_beeping() {
+    let mut emulator = Emu::new();
+    assert!(!emulator.is_beeping());
+    emulator.sound_timer = 1;
+    assert!(emulator.is_beeping());
+    emulator.decrement_sound();
+    assert!(!emulator.is_beeping());
+}
+
+#[test]
+fn test_with_state() {
+    // 8XY4 at 0x300, adding V1 to V0 with a carry
+    let mut memory = vec![0; 0x302];
+    memory[0x300] = 0x80;
+    memory[0x301] = 0x14;
+    let mut registers = [0; 16];
+    registers[0x0] = 0xf0;
+    registers[0x1] = 0x20;
+
+    let mut emulator = Emu::with_state(0x300, 0x123, registers, memory).unwrap();
+    assert_eq!(emulator.memory.len(), 4096);
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.pc, 0x302);
+    assert_eq!(emulator.i, 0x123);
+    assert_eq!(emulator.variables[0x0], 0x10);
+    assert_eq!(emulator.variables[0xf], 1);
+
+    assert!(matches!(
+        Emu::with_state(0x200, 0, [0; 16], vec![0; 4097]),
+        Err(EmulationError::LoadingError)
+    ));
+}
+
+#[test]
+fn test_phosphor_decay() {
+    let mut emulator = Emu::new();
+    assert!(emulator.decay().is_none());
+    emulator.enable_decay(4);
+
+    emulator.pixels[0] = true;
+    emulator.tick_decay();
+    assert_eq!(emulator.decay().unwrap()[0], 255);
+
+    // turned off one frame ago, so it's partway faded
+    emulator.pixels[0] = false;
+    emulator.tick_decay();
+    let brightness = emulator.decay().unwrap()[0];
+    assert!(brightness > 0 && brightness < 255);
+
+    for _ in 0..3 {
+        emulator.tick_decay();
+    }
+    assert_eq!(emulator.decay().unwrap()[0], 0);
+}
+
+#[test]
+fn test_load_store_index_behavior() {
+    // FX55 then FX65 with X = 3, starting from I = 0x300
+    let expected = [
+        (LoadStoreBehavior::Unchanged, 0x300),
+        (LoadStoreBehavior::Cosmac, 0x304),
+        (LoadStoreBehavior::Hp48, 0x303),
+    ];
+    for (behavior, i_after) in expected {
+        let mut emulator = Emu::new();
+        emulator.quirks.load_store = behavior;
+        emulator.variables[..4].copy_from_slice(&[1, 2, 3, 4]);
+        emulator.i = 0x300;
+        emulator.decode_and_execute(0xf355).unwrap();
+        assert_eq!(emulator.memory[0x300..0x305], [1, 2, 3, 4, 0]);
+        assert_eq!(emulator.i, i_after);
+
+        emulator.variables[..4].copy_from_slice(&[0; 4]);
+        emulator.i = 0x300;
+        emulator.decode_and_execute(0xf365).unwrap();
+        assert_eq!(emulator.variables[..5], [1, 2, 3, 4, 0]);
+        assert_eq!(emulator.i, i_after);
+    }
+
+    assert_eq!(
+        Quirks::preset("chip48").unwrap().load_store,
+        LoadStoreBehavior::Hp48
+    );
+}
+
+#[test]
+fn test_watch_register() {
+    // 6312 sets V3, 6412 sets V4, 6312 sets V3 again without changing it
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x63, 0x12, 0x64, 0x12, 0x63, 0x12])
+        .unwrap();
+    emulator.add_watch(WatchTarget::Register(0x3));
+    for _ in 0..3 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+
+    let hits = emulator.take_watch_hits();
+    assert_eq!(
+        hits,
+        vec![WatchHit {
+            target: WatchTarget::Register(0x3),
+            pc: 0x200,
+            old: 0x00,
+            new: 0x12,
+        }]
+    );
+    assert!(emulator.take_watch_hits().is_empty());
+}
+
+#[test]
+fn test_seeded_random_gen() {
+    // CXNN with NN = 0xFF, so the whole random byte shows up in V0
+    let stream = |seed| {
+        let mut emulator = Emu::new();
+        emulator.seed_rng(seed);
+        (0..100)
+            .map(|_| {
+                emulator.decode_and_execute(0xc0ff).unwrap();
+                emulator.variables[0x0]
+            })
+            .collect::<Vec<u8>>()
+    };
+    assert_eq!(stream(1234), stream(1234));
+    assert_ne!(stream(1234), stream(4321));
+}
+
+#[test]
+fn test_peek_instruction() {
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x60, 0x10, 0x00, 0xe0]).unwrap();
+
+    let next = emulator.peek_instruction().unwrap();
+    assert_eq!(next, Instruction::SetRegister { x: 0x0, nn: 0x10 });
+    assert_eq!(next.to_string(), "LD V0, 0x10");
+    assert_eq!(emulator.pc, 0x200); // peeking doesn't move anything
+    assert_eq!(emulator.variables[0x0], 0x00);
+
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.pc, 0x202);
+    assert_eq!(
+        emulator.peek_instruction().unwrap(),
+        Instruction::ClearScreen
+    );
+
+    emulator.pc = 0xfff;
+    assert!(matches!(
+        emulator.peek_instruction(),
+        Err(EmulationError::VacantMemory)
+    ));
+}
+
+#[test]
+fn test_draw_font_glyph() {
+    // point I at the F glyph with FX29, then draw it at (0, 0)
+    let mut emulator = Emu::new();
+    emulator.variables[0x0] = 0xf;
+    emulator.decode_and_execute(0xf029).unwrap();
+    assert_eq!(emulator.i, 0x09b);
+    emulator.decode_and_execute(0xd125).unwrap();
+
+    let glyph = [0xf0, 0x80, 0xf0, 0x80, 0x80];
+    for (y, row) in glyph.iter().enumerate() {
+        for x in 0..8 {
+            let expected = row & (0x80 >> x) != 0;
+            assert_eq!(
+                emulator.pixels[x + y * 64],
+                expected,
+                "pixel ({}, {})",
+                x,
+                y
+            );
+        }
+    }
+    assert_eq!(emulator.pixels.iter().filter(|pixel| **pixel).count(), 11);
+}
+
+#[test]
+fn test_breakpoints() {
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x60, 0x01, 0x60, 0x02]).unwrap();
+    emulator.add_breakpoint(0x202);
+    assert!(!emulator.at_breakpoint());
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(emulator.at_breakpoint());
+    assert!(emulator.remove_breakpoint(0x202));
+    assert!(!emulator.remove_breakpoint(0x202));
+    assert!(!emulator.at_breakpoint());
+}
+
+#[test]
+fn test_disassemble() {
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x60, 0x10, 0xa2, 0x50, 0xff, 0xff])
+        .unwrap();
+    assert_eq!(
+        emulator.disassemble(0x200, 3),
+        vec![
+            "0x200  6010  LD V0, 0x10",
+            "0x202  A250  LD I, 0x250",
+            "0x204  FFFF  ???"
+        ]
+    );
+    // stops at the end of memory instead of going past it
+    assert_eq!(emulator.disassemble(0xffe, 4).len(), 1);
+}
+
+#[test]
+fn test_framebuffer_packing() {
+    let mut emulator = Emu::new();
+    emulator.pixels[0] = true; // top left, most significant bit of the first byte
+    emulator.pixels[7] = true;
+    emulator.pixels[63] = true; // top right
+    emulator.pixels[64 * 31 + 8] = true; // bottom row
+    let packed = emulator.framebuffer_packed();
+    assert_eq!(packed.len(), 256);
+    assert_eq!(packed[0], 0b1000_0001);
+    assert_eq!(packed[7], 0b0000_0001);
+    assert_eq!(packed[8 * 31 + 1], 0b1000_0000);
+
+    let mut restored = Emu::new();
+    restored.load_framebuffer_packed(&packed).unwrap();
+    assert_eq!(restored.pixels, emulator.pixels);
+    assert!(matches!(
+        restored.load_framebuffer_packed(&packed[1..]),
+        Err(EmulationError::FramebufferSize)
+    ));
+
+    // drawing every byte back as a one row sprite gives the same picture
+    let mut drawn = Emu::new();
+    drawn.i = 0x300;
+    for (index, byte) in packed.iter().enumerate() {
+        drawn.memory[0x300] = *byte;
+        drawn.variables[0x0] = (index % 8 * 8) as u8;
+        drawn.variables[0x1] = (index / 8) as u8;
+        drawn.decode_and_execute(0xd011).unwrap();
+    }
+    assert_eq!(drawn.pixels, emulator.pixels);
+}
+
+#[test]
+fn test_step_result() {
+    // DXYN only asks to wait for the next frame with the quirk on
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xd0, 0x05, 0xd0, 0x05]).unwrap();
+    assert_eq!(
+        emulator.fetch_decode_execute_instr().unwrap(),
+        StepResult::Normal
+    );
+    emulator.quirks.display_wait = true;
+    assert_eq!(
+        emulator.fetch_decode_execute_instr().unwrap(),
+        StepResult::AwaitingVBlank
+    );
+
+    // FX0A waits for as long as no key is held
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xf3, 0x0a]).unwrap();
+    assert_eq!(
+        emulator.fetch_decode_execute_instr().unwrap(),
+        StepResult::AwaitingKey
+    );
+    assert_eq!(emulator.pc(), 0x200);
+    emulator.keypress(0x7);
+    assert_eq!(
+        emulator.fetch_decode_execute_instr().unwrap(),
+        StepResult::Normal
+    );
+    assert_eq!(emulator.pc(), 0x202);
+}
+
+#[test]
+fn test_read_rom_memory_size() {
+    // bigger than a standard CHIP-8 can hold, but well within XO-CHIP
+    let rom = vec![0xab; 8000];
+    assert!(matches!(
+        Emu::new().read_rom(rom.clone()),
+        Err(EmulationError::LoadingError)
+    ));
+
+    let mut emulator = Emu::builder().xo_chip(true).build();
+    emulator.read_rom(rom).unwrap();
+    assert_eq!(emulator.memory.len(), XO_CHIP_MEMORY_SIZE);
+    assert_eq!(emulator.memory[0x200 + 7999], 0xab);
+    assert_eq!(emulator.memory[0x200 + 8000], 0);
+
+    // the reserved region still counts against the space
+    let mut emulator = Emu::new();
+    assert!(emulator.read_rom(vec![0; MEMORY_SIZE - 0x200]).is_ok());
+    assert!(emulator.read_rom(vec![0; MEMORY_SIZE - 0x1ff]).is_err());
+}
+
+#[test]
+fn test_read_rom_clears_leftovers() {
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xaa; 0x100]).unwrap();
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    assert_eq!(emulator.memory_range(0x200, 2), &[0x12, 0x00]);
+    assert!(emulator.memory[0x202..].iter().all(|byte| *byte == 0));
+    // the font is still there
+    assert_eq!(
+        emulator.memory_range(0x050, 5),
+        &[0xF0, 0x90, 0x90, 0x90, 0xF0]
+    );
+
+    let mut emulator = Emu::builder().preserve_memory(true).build();
+    emulator.read_rom(vec![0xaa; 0x100]).unwrap();
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    assert_eq!(emulator.memory_range(0x202, 2), &[0xaa, 0xaa]);
+}
+
+#[test]
+fn test_hires_clip_counter() {
+    // an 8 row sprite drawn 5 rows from the bottom of the hires screen
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x00, 0xff, 0x61, 59, 0xa0, 0x50, 0xd0, 0x18])
+        .unwrap();
+    emulator.quirks.clip_counter = true;
+    for _ in 0..4 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+    assert_eq!(emulator.pixels.len(), 128 * 64);
+    assert_eq!(emulator.variables[0xf], 3);
+
+    // the usual flag without the quirk
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x00, 0xff, 0x61, 59, 0xa0, 0x50, 0xd0, 0x18])
+        .unwrap();
+    for _ in 0..4 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+    assert_eq!(emulator.variables[0xf], 0);
+}
+
+#[test]
+fn test_sprite_start_wraps_to_resolution() {
+    // draws a single pixel sprite at (VX, VY) == (200, 100) and finds where it landed
+    let lit_pixel = |hires: bool, clamp: bool| {
+        let mut emulator = Emu::new();
+        emulator.read_rom(vec![0xd0, 0x11, 0x80]).unwrap();
+        emulator.set_hires(hires);
+        emulator.quirks.clamp_sprite_start = clamp;
+        emulator.variables[0x0] = 200;
+        emulator.variables[0x1] = 100;
+        emulator.i = 0x202;
+        emulator.fetch_decode_execute_instr().unwrap();
+        let index = emulator.pixels.iter().position(|lit| *lit).unwrap();
+        let width = emulator.screen_width();
+        (index % width, index / width)
+    };
+
+    assert_eq!(lit_pixel(false, false), (200 & 63, 100 & 31));
+    assert_eq!(lit_pixel(true, false), (200 & 127, 100 & 63));
+    assert_eq!(lit_pixel(false, true), (63, 31));
+    assert_eq!(lit_pixel(true, true), (127, 63));
+}
+
+/// a hires emulator with a solid 16 x 16 sprite at 0x300 for the DXY0 tests,
+/// ready to draw it at (`V0`, `V1`)
+#[cfg(test)]
+fn big_sprite_emulator() -> Emu {
+    let mut emulator = Emu::new();
+    emulator.set_hires(true);
+    emulator.memory[0x300..0x320].fill(0xff);
+    emulator.i = 0x300;
+    emulator
+}
+
+/// draws the 16 x 16 sprite with `D010` at (`x`, `y`)
+#[cfg(test)]
+fn draw_big_sprite(emulator: &mut Emu, x: u8, y: u8) {
+    emulator.variables[0x0] = x;
+    emulator.variables[0x1] = y;
+    emulator.display(0x0, 0x1, 0).unwrap();
+}
+
+#[test]
+fn test_big_sprite_collision() {
+    // drawing the same sprite twice collides and leaves nothing behind
+    let mut emulator = big_sprite_emulator();
+    draw_big_sprite(&mut emulator, 10, 10);
+    assert_eq!(emulator.variables[0xf], 0);
+    assert_eq!(emulator.pixels.iter().filter(|lit| **lit).count(), 256);
+    draw_big_sprite(&mut emulator, 10, 10);
+    assert_eq!(emulator.variables[0xf], 1);
+    assert!(emulator.pixels.iter().all(|lit| !lit));
+
+    // partly overlapping sprites collide, and only the overlap gets turned off
+    let mut emulator = big_sprite_emulator();
+    draw_big_sprite(&mut emulator, 10, 10);
+    draw_big_sprite(&mut emulator, 18, 14);
+    assert_eq!(emulator.variables[0xf], 1);
+    assert_eq!(
+        emulator.pixels.iter().filter(|lit| **lit).count(),
+        256 * 2 - 8 * 12 * 2
+    );
+    assert!(!emulator.pixels[20 + 20 * 128]); // in the overlap
+    assert!(emulator.pixels[12 + 12 * 128]); // only in the first sprite
+    assert!(emulator.pixels[30 + 28 * 128]); // only in the second sprite
+}
+
+#[test]
+fn test_big_sprite_clipping() {
+    let lit_at = |emulator: &Emu, x: usize, y: usize| emulator.pixels[x + y * 128];
+    let lit_count = |emulator: &Emu| emulator.pixels.iter().filter(|lit| **lit).count();
+
+    // right edge: only the 8 columns that fit get drawn, nothing wraps to the left
+    let mut emulator = big_sprite_emulator();
+    draw_big_sprite(&mut emulator, 120, 0);
+    assert_eq!(lit_count(&emulator), 8 * 16);
+    assert!(lit_at(&emulator, 127, 15));
+    assert!(!lit_at(&emulator, 0, 0));
+
+    // bottom edge: only the 8 rows that fit get drawn, nothing wraps to the top
+    let mut emulator = big_sprite_emulator();
+    draw_big_sprite(&mut emulator, 0, 56);
+    assert_eq!(lit_count(&emulator), 16 * 8);
+    assert!(lit_at(&emulator, 15, 63));
+    assert!(!lit_at(&emulator, 0, 0));
+
+    // left and top edges: a start past the edge wraps around,
+    // so the sprite sits fully on screen against the top left corner
+    let mut emulator = big_sprite_emulator();
+    draw_big_sprite(&mut emulator, 128, 64);
+    assert_eq!(lit_count(&emulator), 16 * 16);
+    assert!(lit_at(&emulator, 0, 0));
+    assert!(lit_at(&emulator, 15, 15));
+    assert!(!lit_at(&emulator, 16, 16));
+
+    // and the corner clips on two sides at once
+    let mut emulator = big_sprite_emulator();
+    draw_big_sprite(&mut emulator, 124, 60);
+    assert_eq!(lit_count(&emulator), 4 * 4);
+}
+
+#[test]
+fn test_timer_tick_order() {
+    // reads the delay timer into V0 during the frame
+    let delay_seen = |timers_before_cpu: bool| {
+        let mut emulator = Emu::new();
+        emulator.read_rom(vec![0xf0, 0x07]).unwrap();
+        emulator.quirks.timers_before_cpu = timers_before_cpu;
+        emulator.delay_timer = 1;
+        emulator.step_frame(1).unwrap();
+        assert_eq!(emulator.delay_timer, 0);
+        emulator.variables[0x0]
+    };
+    assert_eq!(delay_seen(false), 1);
+    assert_eq!(delay_seen(true), 0);
+}
+
+#[test]
+fn test_framebuffer_hash() {
+    let mut first = Emu::new();
+    let mut second = Emu::new();
+    assert_eq!(first.framebuffer_hash(), second.framebuffer_hash());
+
+    first.pixels[100] = true;
+    second.pixels[100] = true;
+    assert_eq!(first.framebuffer_hash(), second.framebuffer_hash());
+
+    second.pixels[101] = true;
+    assert_ne!(first.framebuffer_hash(), second.framebuffer_hash());
+}
+
+#[test]
+fn test_jump_with_offset_wraps() {
+    // BNNN
+    let mut emulator = Emu::new();
+    emulator.variables[0x0] = 0x20;
+    emulator.jump_with_offset(0x0ff0).unwrap();
+    assert_eq!(emulator.pc, 0x010);
+
+    // BXNN goes through the same wrapping, X is F here so V0 isn't used
+    emulator.quirks.jump_uses_vx = true;
+    emulator.jump_with_offset(0x0ff0).unwrap();
+    assert_eq!(emulator.pc, 0xff0);
+    emulator.variables[0xf] = 0x20;
+    emulator.jump_with_offset(0x0ff0).unwrap();
+    assert_eq!(emulator.pc, 0x010);
+}
+
+#[test]
+fn test_random_memory_never_panics() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    for round in 0..200 {
+        let mut emulator = Emu::builder().xo_chip(round % 4 == 0).build();
+        let mut image = vec![0; emulator.memory.len()];
+        rng.fill(&mut image[..]);
+        emulator.load_memory_image(&image).unwrap();
+        emulator.quirks.zero_opcode_behavior = ZeroOpcodeBehavior::Nop;
+        emulator.quirks.load_store = LoadStoreBehavior::Cosmac;
+        emulator.keypress(rng.gen_range(0..16));
+        // errors are fine, and stepping on past them shakes out even more states
+        for _ in 0..500 {
+            let _ = emulator.fetch_decode_execute_instr();
+        }
+    }
+
+    let mut emulator = Emu::new();
+    assert!(emulator.load_memory_image(&[0; 100]).is_err());
+}
+
+#[test]
+fn test_self_modifying_detection() {
+    // FX55 at 0x202 overwrites the instruction at 0x204 with V0 and V1
+    let rom = vec![0xa2, 0x04, 0xf1, 0x55, 0x00, 0xe0];
+    let mut emulator = Emu::new();
+    emulator.read_rom(rom.clone()).unwrap();
+    emulator.detect_self_modifying(true);
+    emulator.fetch_decode_execute_instr().unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(
+        emulator.take_self_modify_hits(),
+        vec![
+            SelfModifyHit {
+                address: 0x204,
+                pc: 0x202
+            },
+            SelfModifyHit {
+                address: 0x205,
+                pc: 0x202
+            },
+        ]
+    );
+    assert!(emulator.take_self_modify_hits().is_empty());
+
+    // writes well away from the code don't count, and nothing is recorded unless asked
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xa3, 0x00, 0xf1, 0x55]).unwrap();
+    emulator.detect_self_modifying(true);
+    emulator.fetch_decode_execute_instr().unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(emulator.take_self_modify_hits().is_empty());
+
+    let mut emulator = Emu::new();
+    emulator.read_rom(rom).unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(emulator.take_self_modify_hits().is_empty());
+}
+
+#[test]
+fn test_clear_screen_marks_dirty() {
+    for hires in [false, true] {
+        let mut emulator = Emu::new();
+        emulator
+            .read_rom(vec![0xa0, 0x50, 0xd0, 0x05, 0x00, 0xe0])
+            .unwrap();
+        emulator.set_hires(hires);
+        emulator.fetch_decode_execute_instr().unwrap();
+        emulator.fetch_decode_execute_instr().unwrap();
+        assert!(emulator.take_dirty());
+        assert!(!emulator.take_dirty());
+        assert!(emulator.pixels.iter().any(|lit| *lit));
+
+        emulator.fetch_decode_execute_instr().unwrap();
+        assert!(emulator.pixels.iter().all(|lit| !lit));
+        assert_eq!(
+            emulator.pixels.len(),
+            emulator.screen_width() * emulator.screen_height()
+        );
+        assert!(emulator.take_dirty());
+    }
+}
+
+#[test]
+fn test_latched_key_taps() {
+    // a tap of key 5 that starts and ends before EX9E runs
+    let skipped = |latch_key_taps: bool| {
+        let mut emulator = Emu::new();
+        emulator.read_rom(vec![0x60, 0x05, 0xe0, 0x9e]).unwrap();
+        emulator.quirks.latch_key_taps = latch_key_taps;
+        emulator.keypress(0x5);
+        emulator.keyrelease(0x5);
+        emulator.step_frame(2).unwrap();
+        emulator.pc == 0x206
+    };
+    assert!(skipped(true));
+    assert!(!skipped(false));
+
+    // the latch lets go once the frame is over
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xe0, 0x9e, 0xe0, 0x9e]).unwrap();
+    emulator.quirks.latch_key_taps = true;
+    emulator.keypress(0x0);
+    emulator.keyrelease(0x0);
+    emulator.step_frame(1).unwrap();
+    assert_eq!(emulator.pc, 0x204);
+    emulator.pc = 0x202;
+    emulator.step_frame(1).unwrap();
+    assert_eq!(emulator.pc, 0x204);
+}
+
+#[test]
+fn test_resolution_change_clearing() {
+    let mut emulator = Emu::new();
+    emulator.pixels[0] = true;
+    emulator.pixels[LORES_SIZE.0 + 1] = true; // (1, 1)
+    emulator.enable_hires().unwrap();
+    assert_eq!(emulator.pixels.len(), HIRES_SIZE.0 * HIRES_SIZE.1);
+    assert!(emulator.pixels.iter().all(|pixel| !pixel));
+
+    emulator.quirks.clear_on_resolution_change = false;
+    emulator.disable_hires().unwrap();
+    emulator.pixels[0] = true;
+    emulator.pixels[LORES_SIZE.0 + 1] = true;
+    emulator.take_dirty();
+    emulator.enable_hires().unwrap();
+    assert!(emulator.take_dirty());
+    let lit: Vec<usize> = (0..emulator.pixels.len())
+        .filter(|index| emulator.pixels[*index])
+        .collect();
+    let w = HIRES_SIZE.0;
+    assert_eq!(
+        lit,
+        vec![0, 1, w, w + 1, 2 * w + 2, 2 * w + 3, 3 * w + 2, 3 * w + 3]
+    );
+
+    // and back down again gives the original picture
+    emulator.disable_hires().unwrap();
+    assert_eq!(emulator.pixels.len(), LORES_SIZE.0 * LORES_SIZE.1);
+    assert_eq!(emulator.pixels.iter().filter(|pixel| **pixel).count(), 2);
+    assert!(emulator.pixels[0] && emulator.pixels[LORES_SIZE.0 + 1]);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_pixels_accessor() {
+    // LD V0, 2; LD I, font for 0; DRW V0, V0, 5
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x60, 0x02, 0xa0, 0x50, 0xd0, 0x05])
+        .unwrap();
+    for _ in 0..3 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+    assert_eq!(emulator.pixels(), &emulator.pixels[..]);
+    assert_eq!(emulator.pixels().len(), LORES_SIZE.0 * LORES_SIZE.1);
+    // top row of the 0 is 0xF0, drawn from (2, 2)
+    let row = &emulator.pixels()[2 * LORES_SIZE.0..3 * LORES_SIZE.0];
+    assert_eq!(
+        &row[..8],
+        &[false, false, true, true, true, true, false, false]
+    );
+}
+
+#[test]
+fn test_frame_callback() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let frames = Rc::new(RefCell::new(vec![]));
+    let mut emulator = Emu::new();
+    // CLS; HIGH; JP 0x204
+    emulator
+        .read_rom(vec![0x00, 0xe0, 0x00, 0xff, 0x12, 0x04])
+        .unwrap();
+    let seen = Rc::clone(&frames);
+    emulator.set_frame_callback(Box::new(move |pixels, width, height| {
+        assert_eq!(pixels.len(), width * height);
+        seen.borrow_mut().push((width, height));
+    }));
+
+    emulator.step_frame(1).unwrap();
+    for _ in 0..3 {
+        emulator.step_frame(10).unwrap();
+    }
+    assert_eq!(
+        *frames.borrow(),
+        vec![LORES_SIZE, HIRES_SIZE, HIRES_SIZE, HIRES_SIZE]
+    );
+}
+
+#[test]
+fn test_or_draw_mode() {
+    // LD I, font for 0; DRW V0, V0, 5 three times over
+    let rom = vec![0xa0, 0x50, 0xd0, 0x05, 0xd0, 0x05, 0xd0, 0x05];
+    let mut emulator = Emu::new();
+    emulator.read_rom(rom.clone()).unwrap();
+    emulator.set_draw_mode(DrawMode::Or);
+    emulator.fetch_decode_execute_instr().unwrap();
+    let mut lit = 0;
+    for _ in 0..3 {
+        emulator.fetch_decode_execute_instr().unwrap();
+        let now_lit = emulator.pixels.iter().filter(|pixel| **pixel).count();
+        assert!(now_lit >= lit);
+        lit = now_lit;
+        assert_eq!(emulator.variables[0xf], 0);
+    }
+    assert_eq!(lit, 14); // the pixels of the 0 glyph
+
+    // drawing the same thing twice with XOR erases it
+    let mut emulator = Emu::new();
+    emulator.read_rom(rom).unwrap();
+    for _ in 0..3 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+    assert!(emulator.pixels.iter().all(|pixel| !pixel));
+    assert_eq!(emulator.variables[0xf], 1);
+}
+
+#[test]
+fn test_call_return_round_trip() {
+    // 0x200: CALL 0x208, 0x202: LD V0, 1, ..., 0x208: RET
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x22, 0x08, 0x60, 0x01, 0, 0, 0, 0, 0x00, 0xee])
+        .unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.pc, 0x208);
+    assert_eq!(emulator.the_stack, vec![0x202]);
+
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.pc, 0x202);
+    assert!(emulator.the_stack.is_empty());
+
+    // a second return has nowhere to go
+    emulator.pc = 0x208;
+    assert!(matches!(
+        emulator.fetch_decode_execute_instr(),
+        Err(EmulationError::StackUnderflow)
+    ));
+}
+
+#[test]
+fn test_memory_bytes_limits() {
+    for size in [MEMORY_SIZE, XO_CHIP_MEMORY_SIZE, 0x1800] {
+        let mut emulator = Emu::builder().memory_bytes(size).build();
+        assert_eq!(emulator.memory.len(), size);
+
+        // a ROM filling everything from 0x200 fits, one byte more doesn't
+        assert!(emulator.read_rom(vec![0; size - 0x200 + 1]).is_err());
+        let mut rom = vec![0; size - 0x200];
+        // LD I, 0xFFF; LD V0..V1 from I
+        rom[..4].copy_from_slice(&[0xaf, 0xff, 0xf1, 0x65]);
+        emulator.read_rom(rom).unwrap();
+
+        // reading the last byte works, and reading past it wraps back around to 0
+        // for the power of two sizes and is out of bounds for the others
+        emulator.fetch_decode_execute_instr().unwrap();
+        emulator.i = (size - 1) as u16;
+        emulator.memory[0] = 0x42;
+        let result = emulator.fetch_decode_execute_instr();
+        if size.is_power_of_two() {
+            assert!(result.is_ok());
+            assert_eq!(emulator.variables[1], 0x42);
+        } else {
+            assert!(matches!(result, Err(EmulationError::InvalidAddress)));
+        }
+
+        // an instruction in the last two bytes runs, one starting on the last byte can't
+        emulator.memory[size - 2] = 0x60; // LD V0, 0
+        emulator.pc = (size - 2) as u16;
+        assert!(emulator.fetch_decode_execute_instr().is_ok());
+        emulator.pc = (size - 1) as u16;
+        assert!(matches!(
+            emulator.fetch_decode_execute_instr(),
+            Err(EmulationError::VacantMemory)
+        ));
+    }
+
+    // memory_bytes wins over the XO-CHIP size
+    let emulator = Emu::builder().xo_chip(true).memory_bytes(8192).build();
+    assert_eq!(emulator.memory.len(), 8192);
+
+    // sizes out of range get clamped instead of rejected
+    let emulator = Emu::builder().memory_bytes(1 << 20).build();
+    assert_eq!(emulator.memory.len(), XO_CHIP_MEMORY_SIZE);
+    let emulator = Emu::builder().memory_bytes(0).build();
+    assert_eq!(emulator.memory.len(), PROGRAM_START + 2);
+}
+
+#[test]
+fn test_denied_instructions() {
+    // LD V0, 1; RND V1, 0xFF
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x60, 0x01, 0xc1, 0xff]).unwrap();
+    assert!(emulator.deny("cxnn"));
+    assert!(!emulator.deny("QXNN"));
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(matches!(
+        emulator.fetch_decode_execute_instr(),
+        Err(EmulationError::InstructionDenied { opcode: 0xc1ff })
+    ));
+    assert_eq!(emulator.variables[1], 0);
+}
+
+#[test]
+fn test_advance_to_frame() {
+    // LD V0, 30; LD DT, V0; JP 0x204
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x60, 30, 0xf0, 0x15, 0x12, 0x04])
+        .unwrap();
+    emulator.advance_to_frame(10, 11).unwrap();
+    assert_eq!(emulator.frame_count(), 10);
+    assert_eq!(emulator.delay_timer, 20);
+
+    // going backwards doesn't run anything
+    emulator.advance_to_frame(5, 11).unwrap();
+    assert_eq!(emulator.frame_count(), 10);
+}
+
+#[test]
+fn test_never_drew_watchdog() {
+    // JP 0x200 forever
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    emulator.advance_to_frame(59, 11).unwrap();
+    assert!(!emulator.never_drew_after(60));
+    emulator.step_frame(11).unwrap();
+    assert!(emulator.never_drew_after(60));
+
+    // LD I, font for 0; DRW V0, V0, 5; JP 0x204
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0xa0, 0x50, 0xd0, 0x05, 0x12, 0x04])
+        .unwrap();
+    emulator.advance_to_frame(60, 11).unwrap();
+    assert!(!emulator.never_drew_after(60));
+}
+
+#[test]
+fn test_shift_flag_source() {
+    // V1 = 0x81, V2 = 0x40; SHR V1, V2; then the same for SHL
+    let rom = |n: u8| vec![0x61, 0x81, 0x62, 0x40, 0x81, 0x20 | n];
+    let shift = |n: u8, shift_uses_vy: bool| {
+        let mut emulator = Emu::new();
+        emulator.quirks.shift_uses_vy = shift_uses_vy;
+        emulator.read_rom(rom(n)).unwrap();
+        for _ in 0..3 {
+            emulator.fetch_decode_execute_instr().unwrap();
+        }
+        (emulator.variables[1], emulator.variables[0xf])
+    };
+
+    // VX's own bits get shifted out
+    assert_eq!(shift(0x6, false), (0x40, 1));
+    assert_eq!(shift(0xe, false), (0x02, 1));
+    // VY's value is what gets shifted, so its bits end up in VF
+    assert_eq!(shift(0x6, true), (0x20, 0));
+    assert_eq!(shift(0xe, true), (0x80, 0));
+
+    // VF as the destination keeps the flag rather than the result
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x6f, 0x02, 0x8f, 0xf6]).unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.variables[0xf], 0);
+}
+
+#[test]
+fn test_restore_framebuffer() {
+    let mut emulator = Emu::new();
+    for index in (0..emulator.pixels.len()).step_by(3) {
+        emulator.pixels[index] = true;
+    }
+    let saved = emulator.framebuffer_clone();
+    let hash = emulator.framebuffer_hash();
+
+    emulator.clear_screen().unwrap();
+    assert_ne!(emulator.framebuffer_hash(), hash);
+    emulator.take_dirty();
+    emulator.restore_framebuffer(&saved).unwrap();
+    assert!(emulator.take_dirty());
+    assert_eq!(emulator.framebuffer_hash(), hash);
+    assert_eq!(emulator.pixels, &saved[..]);
+
+    assert!(matches!(
+        emulator.restore_framebuffer(&saved[1..]),
+        Err(EmulationError::FramebufferSize)
+    ));
+}
+
+#[test]
+fn test_strict_platform() {
+    // LD I, font for 0; DRW V0, V0, 0
+    let rom = vec![0xa0, 0x50, 0xd0, 0x00];
+    let mut emulator = Emu::builder().platform(Platform::Chip8).build();
+    emulator.read_rom(rom.clone()).unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(matches!(
+        emulator.fetch_decode_execute_instr(),
+        Err(EmulationError::OutsidePlatform { opcode: 0xd000 })
+    ));
+
+    let mut emulator = Emu::builder().platform(Platform::Schip).build();
+    emulator.read_rom(rom).unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+
+    assert!(Platform::XoChip.allows("SUPER-CHIP"));
+    assert!(!Platform::Schip.allows("XO-CHIP"));
+}
+
+#[test]
+fn test_initial_keys() {
+    let emulator = Emu::builder().initial_keys(1 << 0x5).build();
+    assert!(emulator.is_key_down(0x5));
+    assert_eq!(emulator.keys.iter().filter(|held| **held).count(), 1);
+
+    // SKP V0 with V0 = 5 skips straight away
+    let mut emulator = Emu::builder().initial_keys(1 << 0x5).build();
+    emulator
+        .read_rom(vec![0x60, 0x05, 0xe0, 0x9e, 0, 0, 0x60, 0x01])
+        .unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.pc, 0x206);
+}
+
+#[test]
+fn test_framebuffer_ascii() {
+    // LD V0, 1; LD I, font for 0; DRW V0, V0, 5
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x60, 0x01, 0xa0, 0x50, 0xd0, 0x05])
+        .unwrap();
+    for _ in 0..3 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+
+    let ascii = emulator.framebuffer_ascii();
+    assert_eq!(ascii.lines().count(), LORES_SIZE.1);
+    assert!(ascii.lines().all(|line| line.len() == LORES_SIZE.0));
+    let corner: Vec<&str> = ascii.lines().take(7).map(|line| &line[..8]).collect();
+    assert_eq!(
+        corner.join("\n"),
+        "\
+........
+.####...
+.#..#...
+.#..#...
+.#..#...
+.####...
+........"
+    );
+}
+
+#[cfg(test)]
+struct ScriptedRandom(Vec<u8>);
+
+#[cfg(test)]
+impl RandomSource for ScriptedRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.0.remove(0)
+    }
+}
+
+#[test]
+fn test_random_source() {
+    // RND V0, 0x0F; RND V1, 0xF0
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xc0, 0x0f, 0xc1, 0xf0]).unwrap();
+    emulator.set_random_source(Box::new(ScriptedRandom(vec![0xff, 0x5a])));
+    emulator.fetch_decode_execute_instr().unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.variables[0], 0x0f);
+    assert_eq!(emulator.variables[1], 0x50);
+}
+
+#[test]
+fn test_read_rom_twice() {
+    // LD V3, 7; LD I, 0x300; CALL 0x20A; ...; 0x20A: HIGH, then JP 0x20C
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![
+            0x63, 0x07, 0xa3, 0x00, 0x22, 0x0a, 0, 0, 0, 0, 0x00, 0xff, 0x12, 0x0c,
+        ])
+        .unwrap();
+    emulator.step_frame(10).unwrap();
+    assert_eq!(emulator.the_stack.len(), 1);
+    assert!(emulator.hires);
+
+    emulator.read_rom(vec![0x60, 0x01]).unwrap();
+    assert_eq!(emulator.pc, 0x200);
+    assert_eq!(emulator.i, 0);
+    assert!(emulator.variables.iter().all(|register| *register == 0));
+    assert!(emulator.the_stack.is_empty());
+    assert!(!emulator.hires);
+    assert_eq!(emulator.pixels.len(), LORES_SIZE.0 * LORES_SIZE.1);
+    // the font is still there
+    assert_eq!(emulator.memory[0x50], 0xf0);
+}
+
+#[test]
+fn test_beep_threshold() {
+    let mut emulator = Emu::new();
+    emulator.sound_timer = 1;
+    assert!(emulator.is_beeping());
+    emulator.quirks.beep_above_one = true;
+    assert!(!emulator.is_beeping());
+    emulator.sound_timer = 2;
+    assert!(emulator.is_beeping());
+
+    emulator.sound_timer = 0;
+    emulator.quirks.beep_above_one = false;
+    assert!(!emulator.is_beeping());
+}
+
+#[test]
+fn test_recent_history() {
+    // LD V0, 1 through LD V5, 6
+    let rom: Vec<u8> = (0..6).flat_map(|n| [0x60 | n, n + 1]).collect();
+    let mut emulator = Emu::new();
+    emulator.read_rom(rom).unwrap();
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(emulator.recent_history().is_empty());
+
+    emulator.keep_history(3);
+    for _ in 0..5 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+    assert_eq!(
+        emulator.recent_history(),
+        vec![(0x206, 0x6304), (0x208, 0x6405), (0x20a, 0x6506)]
+    );
+}
+
+#[test]
+fn test_index_wraps_with_memory_size() {
+    // LD V0, 0x20; ADD I, V0
+    let add = |emulator: &mut Emu, i: u16| {
+        emulator.read_rom(vec![0x60, 0x20, 0xf0, 0x1e]).unwrap();
+        emulator.i = i;
+        emulator.fetch_decode_execute_instr().unwrap();
+        emulator.fetch_decode_execute_instr().unwrap();
+        emulator.i
+    };
+    assert_eq!(add(&mut Emu::new(), 0xff0), 0x010);
+    assert_eq!(add(&mut Emu::new(), 0xfd0), 0xff0);
+    let mut xo_chip = Emu::builder().xo_chip(true).build();
+    assert_eq!(add(&mut xo_chip, 0xff0), 0x1010);
+    assert_eq!(add(&mut xo_chip, 0xfff0), 0x0010);
+
+    // a sprite starting on the last byte of memory continues from address 0
+    for mut emulator in [Emu::new(), Emu::builder().xo_chip(true).build()] {
+        let last = emulator.memory.len() - 1;
+        emulator.read_rom(vec![0xd0, 0x02]).unwrap(); // DRW V0, V0, 2
+        emulator.memory[last] = 0x80;
+        emulator.memory[0] = 0x40;
+        emulator.i = last as u16;
+        emulator.fetch_decode_execute_instr().unwrap();
+        assert!(emulator.pixels[0]);
+        assert!(emulator.pixels[LORES_SIZE.0 + 1]);
+    }
+}
+
+#[test]
+fn test_key_tapped_while_paused() {
+    // LD V0, KEY; then loop
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xf0, 0x0a, 0x12, 0x02]).unwrap();
+    emulator.step_frame(5).unwrap();
+    assert_eq!(emulator.pc, 0x200); // still waiting
+
+    emulator.set_paused(true);
+    emulator.keypress(0x7);
+    emulator.keyrelease(0x7);
+    assert!(emulator.held_keys().is_empty());
+    assert_eq!(emulator.step_frame(5).unwrap(), 0);
+    assert_eq!(emulator.pc, 0x200);
+
+    // the tap comes through as soon as the machine runs again, then lets go
+    emulator.set_paused(false);
+    emulator.step_frame(5).unwrap();
+    assert_eq!(emulator.variables[0], 0x7);
+    assert_eq!(emulator.pc, 0x202);
+    assert!(emulator.held_keys().is_empty());
+
+    // a key still held at the end of the pause stays held
+    emulator.set_paused(true);
+    emulator.keypress(0x3);
+    emulator.set_paused(false);
+    emulator.step_frame(1).unwrap();
+    assert_eq!(emulator.held_keys(), vec![0x3]);
+}
+
+#[test]
+fn test_arithmetic_flags() {
+    // runs 8XYN with VX and VY set up first, returning VX and VF afterwards
+    let run = |x: u8, y: u8, n: u8, vx: u8, vy: u8| {
+        let mut emulator = Emu::new();
+        emulator.read_rom(vec![0x80 | x, (y << 4) | n]).unwrap();
+        emulator.variables[x as usize] = vx;
+        emulator.variables[y as usize] = vy;
+        emulator.fetch_decode_execute_instr().unwrap();
+        (emulator.variables[x as usize], emulator.variables[0xf])
+    };
+
+    // 8XY4: no carry, carry, and landing exactly on 255
+    assert_eq!(run(0x1, 0x2, 0x4, 10, 20), (30, 0));
+    assert_eq!(run(0x1, 0x2, 0x4, 200, 100), (44, 1));
+    assert_eq!(run(0x1, 0x2, 0x4, 200, 55), (255, 0));
+    assert_eq!(run(0x1, 0x2, 0x4, 200, 56), (0, 1));
+
+    // 8XY5: no borrow, borrow, and equal operands (no underflow, so VF is 1)
+    assert_eq!(run(0x1, 0x2, 0x5, 30, 10), (20, 1));
+    assert_eq!(run(0x1, 0x2, 0x5, 10, 30), (236, 0));
+    assert_eq!(run(0x1, 0x2, 0x5, 42, 42), (0, 1));
+
+    // 8XY7: the same, the other way around
+    assert_eq!(run(0x1, 0x2, 0x7, 10, 30), (20, 1));
+    assert_eq!(run(0x1, 0x2, 0x7, 30, 10), (236, 0));
+    assert_eq!(run(0x1, 0x2, 0x7, 42, 42), (0, 1));
+
+    // with VF as X, the flag is what's left over rather than the result
+    assert_eq!(run(0xf, 0x2, 0x4, 200, 100), (1, 1));
+    assert_eq!(run(0xf, 0x2, 0x4, 10, 20), (0, 0));
+    assert_eq!(run(0xf, 0x2, 0x5, 30, 10), (1, 1));
+    assert_eq!(run(0xf, 0x2, 0x7, 30, 10), (0, 0));
+
+    // with VF as Y, its value counts before the flag replaces it
+    assert_eq!(run(0x1, 0xf, 0x4, 250, 10), (4, 1));
+    assert_eq!(run(0x1, 0xf, 0x5, 30, 10), (20, 1));
+}
+
+#[test]
+fn test_display_flag() {
+    // draw the 0 glyph twice at the top left, then once at (VF, VF)
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0xa0, 0x50, 0xd0, 0x15, 0xd0, 0x15, 0xdf, 0xf5])
+        .unwrap();
+    emulator.variables[0xf] = 1;
+    emulator.fetch_decode_execute_instr().unwrap();
+
+    // a flag left over from before gets cleared by a draw that doesn't collide
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.variables[0xf], 0);
+    // and set by one that does
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.variables[0xf], 1);
+    assert!(emulator.pixels.iter().all(|lit| !lit));
+
+    // the position is read before the flag gets written, so VF as X and Y draws at (1, 1)
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.variables[0xf], 0);
+    assert!(emulator.pixels[1 + 64]);
+    assert!(!emulator.pixels[0]);
+}
+
+#[test]
+fn test_is_hires() {
+    // 00FF, 00FE, 00FF
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x00, 0xff, 0x00, 0xfe, 0x00, 0xff])
+        .unwrap();
+    assert!(!emulator.is_hires());
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(emulator.is_hires());
+    assert_eq!(emulator.screen_width(), HIRES_SIZE.0);
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(!emulator.is_hires());
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert!(emulator.is_hires());
+
+    // starting a ROM over goes back to lores
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    assert!(!emulator.is_hires());
+}
+
+#[test]
+fn test_skip_ignores_n() {
+    // 5AB3 with VA and VB equal, then whatever it lands on
+    let run = |skip_ignores_n: bool| {
+        let mut emulator = Emu::builder()
+            .quirks(Quirks {
+                skip_ignores_n,
+                ..Quirks::default()
+            })
+            .build();
+        emulator
+            .read_rom(vec![0x5a, 0xb3, 0x12, 0x00, 0x12, 0x00])
+            .unwrap();
+        emulator.variables[0xa] = 7;
+        emulator.variables[0xb] = 7;
+        emulator.fetch_decode_execute_instr().map(|_| emulator.pc)
+    };
+    assert!(matches!(
+        run(false),
+        Err(EmulationError::UnknownInstruction)
+    ));
+    assert_eq!(run(true).unwrap(), 0x204);
+}
+
+#[test]
+fn test_lit_pixel_count() {
+    // the 0 glyph at x 0, then x 4 right next to it, then x 2 over both of them
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![
+            0xa0, 0x50, 0xd0, 0x05, 0x61, 0x04, 0xd1, 0x05, 0x61, 0x02, 0xd1, 0x05,
+        ])
+        .unwrap();
+    assert_eq!(emulator.lit_pixel_count(), 0);
+    emulator.step_frame(2).unwrap();
+    assert_eq!(emulator.lit_pixel_count(), 14);
+    emulator.step_frame(2).unwrap();
+    assert_eq!(emulator.lit_pixel_count(), 28);
+    // the middle of the top and bottom rows gets turned off, the sides of the middle rows on
+    emulator.step_frame(2).unwrap();
+    assert_eq!(emulator.variables[0xf], 1);
+    assert_eq!(emulator.lit_pixel_count(), 4 + 3 * 6 + 4);
+}
+
+#[test]
+fn test_empty_rom() {
+    let mut emulator = Emu::new();
+    assert!(matches!(
+        emulator.read_rom(vec![]),
+        Err(EmulationError::EmptyRom)
+    ));
+    assert_eq!(EmulationError::EmptyRom.to_string(), "ROM file is empty");
+}
+
+#[test]
+fn test_post_frame_hook() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // ADD V0, 1; JP 0x200
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x70, 0x01, 0x12, 0x00]).unwrap();
+    let calls = Rc::new(Cell::new(0));
+    let counted = Rc::clone(&calls);
+    emulator.set_post_frame_hook(Box::new(move |emulator| {
+        counted.set(counted.get() + 1);
+        emulator.variables[0] += 10;
+    }));
+
+    emulator.step_frame(2).unwrap();
+    assert_eq!((calls.get(), emulator.variables[0]), (1, 11));
+    // the next frame carries on from what the hook left
+    emulator.step_frame(2).unwrap();
+    emulator.step_frame(2).unwrap();
+    assert_eq!((calls.get(), emulator.variables[0]), (3, 33));
+}
+
+#[test]
+fn test_disassemble_annotated() {
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0x63, 0x05, 0xa0, 0x50, 0x00, 0x00])
+        .unwrap();
+    let listing = emulator.disassemble_annotated(0x200, 3);
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("0x200  6305  LD V3, 0x05"));
+    assert!(lines[0].ends_with("; V3 = 0x05"));
+    assert!(lines[1].contains("LD I, 0x050") && lines[1].ends_with("; I = 0x050"));
+    assert!(lines[2].contains("???") && lines[2].contains("; not an instruction"));
+}
+
+#[test]
+fn test_sprite_past_memory() {
+    // draws a 16 x 16 (DXY0) or 8 x 15 (DXYF) sprite with I 10 bytes short of the end of memory
+    let draw = |size: usize, opcode_low: u8| {
+        let mut emulator = Emu::builder().memory_bytes(size).build();
+        emulator.read_rom(vec![0xd0, opcode_low]).unwrap();
+        emulator.set_hires(true);
+        emulator.i = (size - 10) as u16;
+        emulator.fetch_decode_execute_instr()
+    };
+    for opcode_low in [0x10, 0x1f] {
+        // with 4 kB, I wraps back around to the start of memory
+        assert!(draw(MEMORY_SIZE, opcode_low).is_ok());
+        // at sizes that aren't a power of two, running off the end is an error, not a panic
+        assert!(matches!(
+            draw(0x1800, opcode_low),
+            Err(EmulationError::InvalidAddress)
+        ));
+    }
+}
+
+#[test]
+fn test_initial_timers() {
+    let mut emulator = Emu::builder().delay_timer(10).sound_timer(3).build();
+    assert_eq!((emulator.delay(), emulator.sound()), (10, 3));
+    assert!(emulator.is_beeping());
+
+    // loading a ROM doesn't lose them, and they count down from there
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    assert_eq!((emulator.delay(), emulator.sound()), (10, 3));
+    emulator.step_frame(1).unwrap();
+    assert_eq!((emulator.delay(), emulator.sound()), (9, 2));
+
+    assert_eq!(Emu::new().delay(), 0);
+}
+
+#[test]
+fn test_dimensions() {
+    // HIGH; LOW
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x00, 0xff, 0x00, 0xfe]).unwrap();
+    assert_eq!(emulator.dimensions(), LORES_SIZE);
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.dimensions(), HIRES_SIZE);
+    assert_eq!(emulator.pixels.len(), HIRES_SIZE.0 * HIRES_SIZE.1);
+    emulator.fetch_decode_execute_instr().unwrap();
+    assert_eq!(emulator.dimensions(), LORES_SIZE);
+    assert_eq!(emulator.pixels.len(), LORES_SIZE.0 * LORES_SIZE.1);
+}
+
+#[test]
+fn test_run_until_halt() {
+    // draw the 0 glyph, then jump to that same jump forever
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0xa0, 0x50, 0xd0, 0x05, 0x12, 0x04])
+        .unwrap();
+    assert_eq!(emulator.run_until_halt(1000).unwrap(), 2);
+    assert_eq!(emulator.pc(), 0x204);
+    assert_eq!(emulator.lit_pixel_count(), 14);
+    // already there, so nothing more runs
+    assert_eq!(emulator.run_until_halt(1000).unwrap(), 0);
+
+    // a loop that never settles runs out of cycles instead
+    emulator.read_rom(vec![0x70, 0x01, 0x12, 0x00]).unwrap();
+    assert_eq!(emulator.run_until_halt(100).unwrap(), 100);
+
+    // halting on 0000 with the quirk counts too
+    let mut emulator = Emu::builder()
+        .quirks(Quirks {
+            zero_opcode_behavior: ZeroOpcodeBehavior::Halt,
+            ..Quirks::default()
+        })
+        .build();
+    emulator.read_rom(vec![0x60, 0x01]).unwrap();
+    assert_eq!(emulator.run_until_halt(100).unwrap(), 2);
+    assert!(emulator.is_halted());
+}
+
+#[test]
+fn test_pixel_change_log() {
+    // draw a sprite row with just its two outer bits set at (3, 4), then clear the screen
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![
+            0x60, 0x03, 0x61, 0x04, 0xa2, 0x0c, 0xd0, 0x11, 0x00, 0xe0, 0x12, 0x0a, 0x81,
+        ])
+        .unwrap();
+    emulator.log_pixel_changes(true);
+    emulator.step_frame(4).unwrap();
+    let change = |x, y, lit, pc| PixelChange { x, y, lit, pc };
+    assert_eq!(
+        emulator.take_pixel_changes(),
+        vec![change(3, 4, true, 0x206), change(10, 4, true, 0x206)]
+    );
+    emulator.step_frame(1).unwrap();
+    assert_eq!(
+        emulator.take_pixel_changes(),
+        vec![change(3, 4, false, 0x208), change(10, 4, false, 0x208)]
+    );
+
+    // nothing is kept unless asked
+    emulator.log_pixel_changes(false);
+    emulator.read_rom(vec![0xa0, 0x50, 0xd0, 0x05]).unwrap();
+    emulator.step_frame(2).unwrap();
+    assert!(emulator.take_pixel_changes().is_empty());
+}
+
+#[test]
+fn test_zero_random_mask_lint() {
+    // RND V0, 0x00 twice, then RND V1, 0xFF, and around again
+    let rom = vec![0xc0, 0x00, 0xc0, 0x00, 0xc1, 0xff, 0x12, 0x00];
+    let mut emulator = Emu::new();
+    emulator.read_rom(rom.clone()).unwrap();
+    emulator.lint(true);
+    emulator.step_frame(4).unwrap();
+    assert_eq!(
+        emulator.take_warnings(),
+        vec!["CXNN at 0x200 masks with 0x00, so it always gives 0"]
+    );
+    emulator.step_frame(4).unwrap();
+    assert!(emulator.take_warnings().is_empty());
+
+    // a mask that isn't 0 is fine, and nothing is said unless asked
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0xc1, 0xff]).unwrap();
+    emulator.lint(true);
+    emulator.step_frame(1).unwrap();
+    assert!(emulator.take_warnings().is_empty());
+    let mut emulator = Emu::new();
+    emulator.read_rom(rom).unwrap();
+    emulator.step_frame(1).unwrap();
+    assert!(emulator.take_warnings().is_empty());
+}
+
+#[test]
+fn test_add_immediate_sets_vf() {
+    // ADD V1, 0x20 with V1 at 0xF0 and VF at 7 beforehand
+    let run = |add_immediate_sets_vf: bool, v1: u8| {
+        let mut emulator = Emu::builder()
+            .quirks(Quirks {
+                add_immediate_sets_vf,
+                ..Quirks::default()
+            })
+            .build();
+        emulator.read_rom(vec![0x71, 0x20]).unwrap();
+        emulator.variables[0x1] = v1;
+        emulator.variables[0xf] = 7;
+        emulator.fetch_decode_execute_instr().unwrap();
+        (emulator.variables[0x1], emulator.variables[0xf])
+    };
+    // the standard behavior never touches VF
+    assert_eq!(run(false, 0xf0), (0x10, 7));
+    // the quirk puts the carry there, overflowing or not
+    assert_eq!(run(true, 0xf0), (0x10, 1));
+    assert_eq!(run(true, 0x10), (0x30, 0));
+}
+
+#[test]
+fn test_stack_depth() {
+    // CALL 0x204; (0x202) JP 0x202; (0x204) CALL 0x208; (0x206) RET; (0x208) RET
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![
+            0x22, 0x04, 0x12, 0x02, 0x22, 0x08, 0x00, 0xee, 0x00, 0xee,
+        ])
+        .unwrap();
+    assert_eq!(emulator.stack_depth(), 0);
+    emulator.step_frame(2).unwrap();
+    assert_eq!(emulator.stack_depth(), 2);
+    emulator.step_frame(1).unwrap();
+    assert_eq!(emulator.stack_depth(), 1);
+    emulator.step_frame(1).unwrap();
+    assert_eq!(emulator.stack_depth(), 0);
+}
+
+#[test]
+fn test_release_all_keys() {
+    let mut emulator = Emu::builder().initial_keys(0b1010_0000_0000_0001).build();
+    emulator.keypress(0x7);
+    emulator.release_all_keys();
+    assert!(emulator.keys.iter().all(|held| !held));
+
+    // while paused it's held on to like any other release
+    emulator.keypress(0x3);
+    emulator.set_paused(true);
+    emulator.release_all_keys();
+    assert!(emulator.keys[0x3]);
+    emulator.set_paused(false);
+    assert!(!emulator.keys[0x3]);
+}
+
+#[test]
+fn test_memory_fill() {
+    let mut emulator = Emu::builder().memory_fill(0xff).build();
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    // the font and ROM are where they should be
+    assert_eq!(
+        emulator.memory_range(0x050, 5),
+        [0xf0, 0x90, 0x90, 0x90, 0xf0]
+    );
+    assert_eq!(emulator.memory_range(0x200, 2), [0x12, 0x00]);
+    // and everything else is the fill
+    assert!(emulator.memory[..0x050].iter().all(|byte| *byte == 0xff));
+    assert!(emulator.memory[0x0a0..0x200]
+        .iter()
+        .all(|byte| *byte == 0xff));
+    assert!(emulator.memory[0x202..].iter().all(|byte| *byte == 0xff));
+
+    // loading a shorter ROM over it fills in after it again
+    emulator.read_rom(vec![0x00, 0xe0, 0x12, 0x02]).unwrap();
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    assert_eq!(emulator.memory_range(0x202, 2), [0xff, 0xff]);
+    assert!(Emu::new().memory[0x202..].iter().all(|byte| *byte == 0));
+}
+
+#[test]
+fn test_clear_keys() {
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    emulator.keypress(0x1);
+    emulator.keypress(0xf);
+    emulator.clear_keys();
+    assert!(emulator.keys.iter().all(|held| !held));
+    assert!(emulator.tapped.iter().all(|tapped| !tapped));
+
+    // presses waiting on a pause are dropped too
+    emulator.set_paused(true);
+    emulator.keypress(0x2);
+    emulator.clear_keys();
+    emulator.set_paused(false);
+    assert!(!emulator.is_key_down(0x2));
+
+    // a new ROM starts with only the initial keys down
+    let mut emulator = Emu::builder().initial_keys(1 << 0x4).build();
+    emulator.keypress(0x9);
+    emulator.read_rom(vec![0x12, 0x00]).unwrap();
+    assert!(emulator.is_key_down(0x4));
+    assert!(!emulator.is_key_down(0x9));
+}
+
+#[test]
+fn test_collided_row_count() {
+    // a 4 row sprite drawn over itself moved down 2 rows to 62, so 2 rows collide and 2 are clipped
+    let draw = |hires: bool, clip_counter: bool, count_collided_rows: bool| {
+        let mut emulator = Emu::builder()
+            .quirks(Quirks {
+                clip_counter,
+                count_collided_rows,
+                ..Quirks::default()
+            })
+            .build();
+        emulator
+            .read_rom(vec![0x61, 0x3c, 0xd0, 0x14, 0x61, 0x3e, 0xd0, 0x14])
+            .unwrap();
+        emulator.set_hires(hires);
+        emulator.i = 0x050; // the 0 glyph
+        emulator.step_frame(4).unwrap();
+        emulator.variables[0xf]
+    };
+    assert_eq!(draw(true, false, false), 1);
+    assert_eq!(draw(true, false, true), 2);
+    assert_eq!(draw(true, true, false), 4);
+    assert_eq!(draw(true, true, true), 4);
+    // lores doesn't count anything
+    assert_eq!(draw(false, false, true), 1);
+}
+
+#[test]
+fn test_build_with_rom() {
+    let emulator = Emu::builder()
+        .xo_chip(true)
+        .build_with_rom(&[0xff; 0x2000])
+        .unwrap();
+    assert_eq!(emulator.memory_range(0x21ff, 2), [0xff, 0x00]);
+    assert!(matches!(
+        Emu::from_rom(&[0xff; 0x2000]),
+        Err(EmulationError::LoadingError)
+    ));
+    assert!(matches!(Emu::from_rom(&[]), Err(EmulationError::EmptyRom)));
+}
+
+#[test]
+fn test_clear_screen_keeps_vf() {
+    // draw, then clear the screen, with a collision flag already in VF
+    let mut emulator = Emu::from_rom(&[0xa0, 0x50, 0xd0, 0x05, 0x00, 0xe0]).unwrap();
+    emulator.step_frame(2).unwrap();
+    emulator.variables[0xf] = 1;
+    emulator.step_frame(1).unwrap();
+    assert_eq!(emulator.lit_pixel_count(), 0);
+    assert_eq!(emulator.variables[0xf], 1);
+}
+
+#[test]
+fn test_skip_long_load() {
+    // SE V0, 0x00; (0x202) LD I, long 0x1234; (0x206) CLS
+    let run = |skip_long_load: bool| {
+        let mut emulator = Emu::builder()
+            .quirks(Quirks {
+                skip_long_load,
+                ..Quirks::default()
+            })
+            .build();
+        emulator
+            .read_rom(vec![0x30, 0x00, 0xf0, 0x00, 0x12, 0x34, 0x00, 0xe0])
+            .unwrap();
+        emulator.fetch_decode_execute_instr().unwrap();
+        emulator.pc
+    };
+    // the whole 4 byte instruction gets skipped
+    assert_eq!(run(true), 0x206);
+    // without the quirk only the first word is
+    assert_eq!(run(false), 0x204);
+}
+
+#[test]
+fn test_lenient_8xyn() {
+    // 8128, which isn't anything, then whatever comes after it
+    let run = |lenient_8xyn: bool| {
+        let mut emulator = Emu::builder()
+            .quirks(Quirks {
+                lenient_8xyn,
+                ..Quirks::default()
+            })
+            .build();
+        emulator.read_rom(vec![0x81, 0x28, 0x12, 0x02]).unwrap();
+        emulator.variables[0x1] = 3;
+        emulator.variables[0x2] = 5;
+        emulator.fetch_decode_execute_instr().map(|_| emulator)
+    };
+    assert!(matches!(
+        run(false),
+        Err(EmulationError::UnknownInstruction)
+    ));
+    // it just moves on, leaving the registers alone
+    let emulator = run(true).unwrap();
+    assert_eq!(emulator.pc, 0x202);
+    assert_eq!(emulator.variables[0x1], 3);
+    assert_eq!(emulator.variables[0x2], 5);
+    assert_eq!(emulator.variables[0xf], 0);
+}
+
+#[test]
+fn test_register_dump() {
+    // LD V0, 0x12; LD V5, 0xFF; LD V6, 0x01; ADD V5, V6
+    let mut emulator = Emu::from_rom(&[0x60, 0x12, 0x65, 0xff, 0x66, 0x01, 0x85, 0x64]).unwrap();
+    assert_eq!(emulator.register_dump(), [0; 16]);
+    for _ in 0..4 {
+        emulator.fetch_decode_execute_instr().unwrap();
+    }
+    let mut expected = [0; 16];
+    expected[0x0] = 0x12;
+    expected[0x6] = 0x01;
+    expected[0xf] = 1; // the add carried
+    assert_eq!(emulator.register_dump(), expected);
+}
+
+#[test]
+fn test_advance_to_frame_while_paused() {
+    let mut emulator = Emu::from_rom(&[0x12, 0x00]).unwrap();
+    emulator.set_paused(true);
+    let frame = emulator.frame_count();
+    // returns instead of waiting forever on frames that never come
+    emulator.advance_to_frame(frame + 1, 10).unwrap();
+    assert_eq!(emulator.frame_count(), frame);
+
+    emulator.set_paused(false);
+    emulator.advance_to_frame(frame + 1, 10).unwrap();
+    assert_eq!(emulator.frame_count(), frame + 1);
+}
@@ -0,0 +1,210 @@
+// draws the emulator's screen into a ggez window.
+// ggez wants drawing to happen in `draw`, while frames get presented from `update`,
+// so the latest frame is kept around until ggez asks for it.
+// The batch of lit pixels is only rebuilt after a new frame was presented,
+// so a screen that sits still doesn't get sent to the GPU again every draw
+
+use ggez::graphics::{Canvas, Color, DrawParam, InstanceArray, Rect, Text};
+use ggez::{Context, GameResult};
+
+use rite_emu::renderer::{Orientation, Renderer};
+
+/// how many window pixels wide and tall each CHIP-8 pixel is on the 64 x 32 screen,
+/// hires pixels are drawn at half of this so the window stays the same size
+pub const SCALE: f32 = 15.;
+
+/// how dark the scanlines are, 0 for invisible and 1 for black
+const SCANLINE_ALPHA: f32 = 0.35;
+
+/// Shows frames as a grid of squares in a ggez window.
+///
+/// # Fields
+/// * `on_color` - color of lit pixels
+/// * `off_color` - color of unlit pixels
+/// * `framebuffer` - the last frame presented, turned to `orientation`, drawn on the next `draw`
+/// * `width` - width of `framebuffer` in pixels
+/// * `height` - height of `framebuffer` in pixels
+/// * `overlay` - text drawn over the screen, like an error we stopped at
+/// * `lit` - the lit pixels of `framebuffer` as one batch, built on the first `draw`
+/// * `stale` - `lit` doesn't match `framebuffer` anymore, and has to be rebuilt
+/// * `scanlines` - darken the bottom of every row of pixels like an old CRT, see `scanline_rects`
+/// * `scanline_batch` - the scanlines as one batch, rebuilt along with `lit`
+/// * `orientation` - which way up frames get shown, for displays that are mounted rotated or mirrored
+pub struct GgezRenderer {
+    pub on_color: Color,
+    pub off_color: Color,
+    framebuffer: Vec<bool>,
+    width: usize,
+    height: usize,
+    pub overlay: Option<String>,
+    lit: Option<InstanceArray>,
+    stale: bool,
+    pub scanlines: bool,
+    scanline_batch: Option<InstanceArray>,
+    pub orientation: Orientation,
+}
+
+impl GgezRenderer {
+    pub fn new() -> Self {
+        GgezRenderer {
+            on_color: Color::WHITE,
+            off_color: Color::BLACK,
+            framebuffer: vec![false; 64 * 32],
+            width: 64,
+            height: 32,
+            overlay: None,
+            lit: None,
+            stale: true,
+            scanlines: false,
+            scanline_batch: None,
+            orientation: Orientation::default(),
+        }
+    }
+
+    /// draws the last presented frame to the window.
+    /// Unlit pixels are just the background, and lit ones all go out in one batch,
+    /// which is only uploaded again when a different frame has been presented since the last draw
+    pub fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut canvas = Canvas::from_frame(ctx, self.off_color);
+        if self.stale {
+            let rects_drawn = |rects: Vec<Rect>, color: Color| -> Vec<DrawParam> {
+                rects
+                    .into_iter()
+                    .map(|rect| {
+                        DrawParam::default()
+                            .color(color)
+                            .dest([rect.x, rect.y])
+                            .scale([rect.w, rect.h])
+                    })
+                    .collect()
+            };
+            let lit = rects_drawn(self.lit_rects(), self.on_color);
+            self.lit
+                .get_or_insert_with(|| InstanceArray::new(ctx, None))
+                .set(lit);
+            if self.scanlines {
+                // the rows move when the resolution changes, so these have to be redone too
+                let lines = rects_drawn(
+                    self.scanline_rects(),
+                    Color::new(0., 0., 0., SCANLINE_ALPHA),
+                );
+                self.scanline_batch
+                    .get_or_insert_with(|| InstanceArray::new(ctx, None))
+                    .set(lines);
+            }
+            self.stale = false;
+        }
+        if let Some(lit) = &self.lit {
+            canvas.draw(lit, DrawParam::default());
+        }
+        // a handful of translucent lines over everything, cheap enough to do every draw
+        if let Some(lines) = self.scanline_batch.as_ref().filter(|_| self.scanlines) {
+            canvas.draw(lines, DrawParam::default());
+        }
+
+        if let Some(overlay) = &self.overlay {
+            canvas.draw(
+                &Text::new(overlay.as_str()),
+                DrawParam::default().color(Color::RED).dest([10., 10.]),
+            );
+        }
+
+        canvas.finish(ctx)
+    }
+
+    /// where each lit pixel goes in the window.
+    /// Hires pixels are 7.5 window pixels across, so edges get rounded to whole window pixels,
+    /// with neighbors sharing the same rounded edge so there are never gaps or overlaps between them
+    fn lit_rects(&self) -> Vec<Rect> {
+        let edge = |cell: usize| self.edge(cell);
+        self.framebuffer
+            .iter()
+            .enumerate()
+            .filter(|(_, lit)| **lit)
+            .map(|(index, _)| {
+                let (x, y) = (index % self.width, index / self.width);
+                Rect::new(
+                    edge(x),
+                    edge(y),
+                    edge(x + 1) - edge(x),
+                    edge(y + 1) - edge(y),
+                )
+            })
+            .collect()
+    }
+
+    /// where the scanlines go in the window: a strip across the bottom third of each row of pixels,
+    /// at least one window pixel tall, so they follow the resolution the same way the pixels do
+    fn scanline_rects(&self) -> Vec<Rect> {
+        let window_width = self.edge(self.width);
+        (0..self.height)
+            .map(|y| {
+                let (top, bottom) = (self.edge(y), self.edge(y + 1));
+                let thickness = ((bottom - top) / 3.).round().max(1.);
+                Rect::new(0., bottom - thickness, window_width, thickness)
+            })
+            .collect()
+    }
+
+    /// the window coordinate of the edge before pixel `cell`, along either axis,
+    /// rounded to a whole window pixel. The long side of the screen is always
+    /// 64 pixels of `SCALE`, even when it's turned on its side
+    fn edge(&self, cell: usize) -> f32 {
+        let scale = SCALE * 64. / self.width.max(self.height) as f32;
+        (cell as f32 * scale).round()
+    }
+}
+
+impl Renderer for GgezRenderer {
+    fn present(&mut self, framebuffer: &[bool], width: usize, height: usize) {
+        self.framebuffer = self.orientation.apply(framebuffer, width, height);
+        (self.width, self.height) = self.orientation.dimensions(width, height);
+        self.stale = true;
+    }
+}
+
+#[test]
+fn test_lit_rects_tile_exactly() {
+    let mut renderer = GgezRenderer::new();
+    let (width, height) = rite_emu::emu::HIRES_SIZE;
+    let mut framebuffer = vec![false; width * height];
+    framebuffer[0] = true;
+    framebuffer[1] = true;
+    framebuffer[width + 5] = true;
+    renderer.present(&framebuffer, width, height);
+    let rects = renderer.lit_rects();
+    assert_eq!(rects.len(), 3);
+    // neighbors share an edge, with no gap between them
+    assert_eq!(rects[0].x + rects[0].w, rects[1].x);
+
+    // a fully lit screen covers the window exactly, on whole window pixels
+    renderer.present(&vec![true; width * height], width, height);
+    let rects = renderer.lit_rects();
+    assert_eq!(rects.len(), width * height);
+    assert!(rects.iter().all(|rect| [rect.x, rect.y, rect.w, rect.h]
+        .iter()
+        .all(|v| v.fract() == 0.)));
+    let area: f32 = rects.iter().map(|rect| rect.w * rect.h).sum();
+    assert_eq!(area, (SCALE * 64.) * (SCALE * 32.));
+}
+
+#[test]
+fn test_scanline_rects() {
+    let mut renderer = GgezRenderer::new();
+    let rows = renderer.scanline_rects();
+    assert_eq!(rows.len(), 32);
+    // one line along the bottom of each row, 5 window pixels of the 15
+    assert_eq!(rows[0], Rect::new(0., 10., SCALE * 64., 5.));
+    assert_eq!(rows[31].y + rows[31].h, SCALE * 32.);
+
+    // in hires there are twice as many, still inside their rows and on whole window pixels
+    let (width, height) = rite_emu::emu::HIRES_SIZE;
+    renderer.present(&vec![false; width * height], width, height);
+    let rows = renderer.scanline_rects();
+    assert_eq!(rows.len(), height);
+    for (y, row) in rows.iter().enumerate() {
+        assert!(row.y >= renderer.edge(y) && row.y + row.h == renderer.edge(y + 1));
+        assert_eq!(row.y.fract(), 0.);
+        assert!(row.h >= 1.);
+    }
+}
@@ -0,0 +1,422 @@
+// decoding opcodes into something readable without executing them,
+// for debuggers, disassembly, and anything else that wants to look ahead.
+// Each variant is named after the `Emu` method that executes it.
+
+use crate::emu::EmulationError;
+use std::fmt::Display;
+
+/// A decoded CHIP-8 instruction.
+/// `x` and `y` are register numbers, `n`/`nn`/`nnn` are the immediate values
+/// in the same nibbles as the opcode tables use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    ClearScreen,                        // 00E0
+    ReturnFromSubroutine,               // 00EE
+    DisableHires,                       // 00FE
+    EnableHires,                        // 00FF
+    Jump { nnn: u16 },                  // 1NNN
+    CallSubroutine { nnn: u16 },        // 2NNN
+    SkipIfVxEqNn { x: u8, nn: u8 },     // 3XNN
+    SkipIfVxNeqNn { x: u8, nn: u8 },    // 4XNN
+    SkipIfVxEqVy { x: u8, y: u8 },      // 5XY0
+    SetRegister { x: u8, nn: u8 },      // 6XNN
+    AddValToRegister { x: u8, nn: u8 }, // 7XNN
+    SetVxToVy { x: u8, y: u8 },         // 8XY0
+    VxOreqVy { x: u8, y: u8 },          // 8XY1
+    VxAndeqVy { x: u8, y: u8 },         // 8XY2
+    VxXoreqVy { x: u8, y: u8 },         // 8XY3
+    VxPluseqVy { x: u8, y: u8 },        // 8XY4
+    VxMinuseqVy { x: u8, y: u8 },       // 8XY5
+    ShiftRight1bit { x: u8, y: u8 },    // 8XY6
+    VxEqualsVyMinusVx { x: u8, y: u8 }, // 8XY7
+    ShiftLeft1bit { x: u8, y: u8 },     // 8XYE
+    SkipIfVxNeqVy { x: u8, y: u8 },     // 9XY0
+    SetIndexRegister { nnn: u16 },      // ANNN
+    JumpWithOffset { nnn: u16 },        // BNNN
+    RandomGen { x: u8, nn: u8 },        // CXNN
+    Display { x: u8, y: u8, n: u8 },    // DXYN
+    SkipIfKey { x: u8 },                // EX9E
+    SkipIfNotKey { x: u8 },             // EXA1
+    SetVxToDelaytmr { x: u8 },          // FX07
+    GetKey { x: u8 },                   // FX0A
+    SetDelaytmrToVx { x: u8 },          // FX15
+    SetSoundtmrToVx { x: u8 },          // FX18
+    AddToIndex { x: u8 },               // FX1E
+    FontCharacter { x: u8 },            // FX29
+    BinaryDecimalConversion { x: u8 },  // FX33
+    StoreMemory { x: u8 },              // FX55
+    LoadMemory { x: u8 },               // FX65
+}
+
+/// decodes `opcode` without touching any emulator state.
+/// Fails the same way executing it would for `0000` and unknown opcodes.
+pub fn decode(opcode: u16) -> Result<Instruction, EmulationError> {
+    let instr_type = opcode >> 12;
+    let x = ((opcode >> 8) & 0xf) as u8;
+    let y = ((opcode >> 4) & 0xf) as u8;
+    let n = (opcode & 0xf) as u8;
+    let nn = (opcode & 0xff) as u8;
+    let nnn = opcode & 0xfff;
+
+    let instruction = match instr_type {
+        0x0 => match nnn {
+            0x0e0 => Instruction::ClearScreen,
+            0x0ee => Instruction::ReturnFromSubroutine,
+            0x0fe => Instruction::DisableHires,
+            0x0ff => Instruction::EnableHires,
+            0x000 => return Err(EmulationError::VacantMemory),
+            _ => return Err(EmulationError::UnknownInstruction),
+        },
+        0x1 => Instruction::Jump { nnn },
+        0x2 => Instruction::CallSubroutine { nnn },
+        0x3 => Instruction::SkipIfVxEqNn { x, nn },
+        0x4 => Instruction::SkipIfVxNeqNn { x, nn },
+        0x5 => match n {
+            0x0 => Instruction::SkipIfVxEqVy { x, y },
+            _ => return Err(EmulationError::UnknownInstruction),
+        },
+        0x6 => Instruction::SetRegister { x, nn },
+        0x7 => Instruction::AddValToRegister { x, nn },
+        0x8 => match n {
+            0x0 => Instruction::SetVxToVy { x, y },
+            0x1 => Instruction::VxOreqVy { x, y },
+            0x2 => Instruction::VxAndeqVy { x, y },
+            0x3 => Instruction::VxXoreqVy { x, y },
+            0x4 => Instruction::VxPluseqVy { x, y },
+            0x5 => Instruction::VxMinuseqVy { x, y },
+            0x6 => Instruction::ShiftRight1bit { x, y },
+            0x7 => Instruction::VxEqualsVyMinusVx { x, y },
+            0xe => Instruction::ShiftLeft1bit { x, y },
+            _ => return Err(EmulationError::UnknownInstruction),
+        },
+        0x9 => Instruction::SkipIfVxNeqVy { x, y },
+        0xa => Instruction::SetIndexRegister { nnn },
+        0xb => Instruction::JumpWithOffset { nnn },
+        0xc => Instruction::RandomGen { x, nn },
+        0xd => Instruction::Display { x, y, n },
+        0xe => match nn {
+            0x9e => Instruction::SkipIfKey { x },
+            0xa1 => Instruction::SkipIfNotKey { x },
+            _ => return Err(EmulationError::UnknownInstruction),
+        },
+        0xf => match nn {
+            0x07 => Instruction::SetVxToDelaytmr { x },
+            0x0a => Instruction::GetKey { x },
+            0x15 => Instruction::SetDelaytmrToVx { x },
+            0x18 => Instruction::SetSoundtmrToVx { x },
+            0x1e => Instruction::AddToIndex { x },
+            0x29 => Instruction::FontCharacter { x },
+            0x33 => Instruction::BinaryDecimalConversion { x },
+            0x55 => Instruction::StoreMemory { x },
+            0x65 => Instruction::LoadMemory { x },
+            _ => return Err(EmulationError::UnknownInstruction),
+        },
+        _ => return Err(EmulationError::UnknownInstruction),
+    };
+    Ok(instruction)
+}
+
+/// Describes one opcode pattern, see `supported_opcodes`.
+///
+/// # Fields
+/// * `pattern` - the opcode with its operands as letters, like `FX33`
+/// * `mnemonic` - how the disassembler writes it
+/// * `variant` - which instruction set it comes from
+/// * `implemented` - whether `decode` understands it in this build
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpcodeInfo {
+    pub pattern: &'static str,
+    pub mnemonic: &'static str,
+    pub variant: &'static str,
+    pub implemented: bool,
+}
+
+/// every opcode pattern we know about, with an example of each to try decoding
+const OPCODES: [(&str, &str, &str, u16); 47] = [
+    ("00E0", "CLS", "CHIP-8", 0x00e0),
+    ("00EE", "RET", "CHIP-8", 0x00ee),
+    ("1NNN", "JP addr", "CHIP-8", 0x1200),
+    ("2NNN", "CALL addr", "CHIP-8", 0x2200),
+    ("3XNN", "SE Vx, byte", "CHIP-8", 0x3000),
+    ("4XNN", "SNE Vx, byte", "CHIP-8", 0x4000),
+    ("5XY0", "SE Vx, Vy", "CHIP-8", 0x5000),
+    ("6XNN", "LD Vx, byte", "CHIP-8", 0x6000),
+    ("7XNN", "ADD Vx, byte", "CHIP-8", 0x7000),
+    ("8XY0", "LD Vx, Vy", "CHIP-8", 0x8000),
+    ("8XY1", "OR Vx, Vy", "CHIP-8", 0x8001),
+    ("8XY2", "AND Vx, Vy", "CHIP-8", 0x8002),
+    ("8XY3", "XOR Vx, Vy", "CHIP-8", 0x8003),
+    ("8XY4", "ADD Vx, Vy", "CHIP-8", 0x8004),
+    ("8XY5", "SUB Vx, Vy", "CHIP-8", 0x8005),
+    ("8XY6", "SHR Vx, Vy", "CHIP-8", 0x8006),
+    ("8XY7", "SUBN Vx, Vy", "CHIP-8", 0x8007),
+    ("8XYE", "SHL Vx, Vy", "CHIP-8", 0x800e),
+    ("9XY0", "SNE Vx, Vy", "CHIP-8", 0x9000),
+    ("ANNN", "LD I, addr", "CHIP-8", 0xa000),
+    ("BNNN", "JP V0, addr", "CHIP-8", 0xb000),
+    ("CXNN", "RND Vx, byte", "CHIP-8", 0xc000),
+    ("DXYN", "DRW Vx, Vy, nibble", "CHIP-8", 0xd001),
+    ("EX9E", "SKP Vx", "CHIP-8", 0xe09e),
+    ("EXA1", "SKNP Vx", "CHIP-8", 0xe0a1),
+    ("FX07", "LD Vx, DT", "CHIP-8", 0xf007),
+    ("FX0A", "LD Vx, K", "CHIP-8", 0xf00a),
+    ("FX15", "LD DT, Vx", "CHIP-8", 0xf015),
+    ("FX18", "LD ST, Vx", "CHIP-8", 0xf018),
+    ("FX1E", "ADD I, Vx", "CHIP-8", 0xf01e),
+    ("FX29", "LD F, Vx", "CHIP-8", 0xf029),
+    ("FX33", "LD B, Vx", "CHIP-8", 0xf033),
+    ("FX55", "LD [I], Vx", "CHIP-8", 0xf055),
+    ("FX65", "LD Vx, [I]", "CHIP-8", 0xf065),
+    ("DXY0", "DRW Vx, Vy, 0", "SUPER-CHIP", 0xd000),
+    ("00CN", "SCD nibble", "SUPER-CHIP", 0x00c1),
+    ("00FB", "SCR", "SUPER-CHIP", 0x00fb),
+    ("00FC", "SCL", "SUPER-CHIP", 0x00fc),
+    ("00FD", "EXIT", "SUPER-CHIP", 0x00fd),
+    ("00FE", "LOW", "SUPER-CHIP", 0x00fe),
+    ("00FF", "HIGH", "SUPER-CHIP", 0x00ff),
+    ("FX30", "LD HF, Vx", "SUPER-CHIP", 0xf030),
+    ("FX75", "LD R, Vx", "SUPER-CHIP", 0xf075),
+    ("FX85", "LD Vx, R", "SUPER-CHIP", 0xf085),
+    ("5XY2", "SAVE Vx - Vy", "XO-CHIP", 0x5012),
+    ("5XY3", "LOAD Vx - Vy", "XO-CHIP", 0x5013),
+    ("F000", "LD I, long addr", "XO-CHIP", 0xf000),
+];
+
+/// lists every opcode pattern from CHIP-8, SUPER-CHIP, and XO-CHIP,
+/// along with whether this build can run it yet
+pub fn supported_opcodes() -> Vec<OpcodeInfo> {
+    OPCODES
+        .iter()
+        .map(|(pattern, mnemonic, variant, example)| OpcodeInfo {
+            pattern,
+            mnemonic,
+            variant,
+            implemented: decode(*example).is_ok(),
+        })
+        .collect()
+}
+
+/// finds the pattern from `supported_opcodes` that `opcode` belongs to, like `FX33` for `F233`.
+/// When more than one fits (`D010` is both `DXYN` and `DXY0`) the most specific one wins.
+pub fn pattern_for(opcode: u16) -> Option<&'static str> {
+    let fits = |pattern: &str| {
+        pattern.chars().enumerate().all(|(nibble, digit)| {
+            let value = (opcode >> (12 - nibble * 4)) & 0xf;
+            match digit.to_digit(16) {
+                Some(expected) => expected as u16 == value,
+                None => true, // X, Y, N, and so on stand for anything
+            }
+        })
+    };
+    let literal_digits = |pattern: &str| pattern.chars().filter(char::is_ascii_hexdigit).count();
+
+    OPCODES
+        .iter()
+        .map(|(pattern, _, _, _)| *pattern)
+        .filter(|pattern| fits(pattern))
+        .max_by_key(|pattern| literal_digits(pattern))
+}
+
+/// which of CHIP-8, SUPER-CHIP, and XO-CHIP first had `opcode`, going by `pattern_for`
+pub fn variant_for(opcode: u16) -> Option<&'static str> {
+    let pattern = pattern_for(opcode)?;
+    OPCODES
+        .iter()
+        .find(|(known, _, _, _)| *known == pattern)
+        .map(|(_, _, variant, _)| *variant)
+}
+
+impl Instruction {
+    /// says in words what the instruction does, for annotated listings.
+    /// Where quirks change the behavior, this is the default behavior
+    pub fn describe(&self) -> String {
+        match *self {
+            Self::ClearScreen => "clear the screen".to_string(),
+            Self::ReturnFromSubroutine => "return from the subroutine".to_string(),
+            Self::DisableHires => "switch to the 64 x 32 screen".to_string(),
+            Self::EnableHires => "switch to the 128 x 64 screen".to_string(),
+            Self::Jump { nnn } => format!("jump to 0x{:03X}", nnn),
+            Self::CallSubroutine { nnn } => format!("call the subroutine at 0x{:03X}", nnn),
+            Self::SkipIfVxEqNn { x, nn } => {
+                format!("skip the next instruction if V{:X} == 0x{:02X}", x, nn)
+            }
+            Self::SkipIfVxNeqNn { x, nn } => {
+                format!("skip the next instruction if V{:X} != 0x{:02X}", x, nn)
+            }
+            Self::SkipIfVxEqVy { x, y } => {
+                format!("skip the next instruction if V{:X} == V{:X}", x, y)
+            }
+            Self::SetRegister { x, nn } => format!("V{:X} = 0x{:02X}", x, nn),
+            Self::AddValToRegister { x, nn } => {
+                format!("V{:X} += 0x{:02X}, VF is left alone", x, nn)
+            }
+            Self::SetVxToVy { x, y } => format!("V{:X} = V{:X}", x, y),
+            Self::VxOreqVy { x, y } => format!("V{:X} |= V{:X}", x, y),
+            Self::VxAndeqVy { x, y } => format!("V{:X} &= V{:X}", x, y),
+            Self::VxXoreqVy { x, y } => format!("V{:X} ^= V{:X}", x, y),
+            Self::VxPluseqVy { x, y } => format!("V{:X} += V{:X}, VF = 1 on a carry", x, y),
+            Self::VxMinuseqVy { x, y } => {
+                format!("V{:X} -= V{:X}, VF = 0 on a borrow", x, y)
+            }
+            Self::ShiftRight1bit { x, .. } => {
+                format!("V{:X} >>= 1, VF = the bit shifted out", x)
+            }
+            Self::VxEqualsVyMinusVx { x, y } => {
+                format!("V{:X} = V{:X} - V{:X}, VF = 0 on a borrow", x, y, x)
+            }
+            Self::ShiftLeft1bit { x, .. } => format!("V{:X} <<= 1, VF = the bit shifted out", x),
+            Self::SkipIfVxNeqVy { x, y } => {
+                format!("skip the next instruction if V{:X} != V{:X}", x, y)
+            }
+            Self::SetIndexRegister { nnn } => format!("I = 0x{:03X}", nnn),
+            Self::JumpWithOffset { nnn } => format!("jump to 0x{:03X} + V0", nnn),
+            Self::RandomGen { x, nn } => format!("V{:X} = a random byte & 0x{:02X}", x, nn),
+            Self::Display { x, y, n: 0 } => format!(
+                "draw the 16 x 16 sprite at I at (V{:X}, V{:X}), VF = 1 on a collision",
+                x, y
+            ),
+            Self::Display { x, y, n } => format!(
+                "draw the {} row sprite at I at (V{:X}, V{:X}), VF = 1 on a collision",
+                n, x, y
+            ),
+            Self::SkipIfKey { x } => {
+                format!("skip the next instruction if the key in V{:X} is down", x)
+            }
+            Self::SkipIfNotKey { x } => {
+                format!("skip the next instruction if the key in V{:X} is up", x)
+            }
+            Self::SetVxToDelaytmr { x } => format!("V{:X} = the delay timer", x),
+            Self::GetKey { x } => format!("wait for a key to be pressed, and put it in V{:X}", x),
+            Self::SetDelaytmrToVx { x } => format!("the delay timer = V{:X}", x),
+            Self::SetSoundtmrToVx { x } => format!("the sound timer = V{:X}", x),
+            Self::AddToIndex { x } => format!("I += V{:X}", x),
+            Self::FontCharacter { x } => format!("I = the font glyph for the digit in V{:X}", x),
+            Self::BinaryDecimalConversion { x } => {
+                format!(
+                    "store the decimal digits of V{:X} at I, I + 1, and I + 2",
+                    x
+                )
+            }
+            Self::StoreMemory { x } => format!("store V0 through V{:X} starting at I", x),
+            Self::LoadMemory { x } => format!("load V0 through V{:X} starting at I", x),
+        }
+    }
+}
+
+impl Display for Instruction {
+    /// writes the instruction as an assembly mnemonic,
+    /// following the syntax from Cowgod's CHIP-8 technical reference
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::ClearScreen => write!(f, "CLS"),
+            Self::ReturnFromSubroutine => write!(f, "RET"),
+            Self::DisableHires => write!(f, "LOW"),
+            Self::EnableHires => write!(f, "HIGH"),
+            Self::Jump { nnn } => write!(f, "JP 0x{:03X}", nnn),
+            Self::CallSubroutine { nnn } => write!(f, "CALL 0x{:03X}", nnn),
+            Self::SkipIfVxEqNn { x, nn } => write!(f, "SE V{:X}, 0x{:02X}", x, nn),
+            Self::SkipIfVxNeqNn { x, nn } => write!(f, "SNE V{:X}, 0x{:02X}", x, nn),
+            Self::SkipIfVxEqVy { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Self::SetRegister { x, nn } => write!(f, "LD V{:X}, 0x{:02X}", x, nn),
+            Self::AddValToRegister { x, nn } => write!(f, "ADD V{:X}, 0x{:02X}", x, nn),
+            Self::SetVxToVy { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Self::VxOreqVy { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Self::VxAndeqVy { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Self::VxXoreqVy { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Self::VxPluseqVy { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Self::VxMinuseqVy { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Self::ShiftRight1bit { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Self::VxEqualsVyMinusVx { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Self::ShiftLeft1bit { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Self::SkipIfVxNeqVy { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Self::SetIndexRegister { nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            Self::JumpWithOffset { nnn } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Self::RandomGen { x, nn } => write!(f, "RND V{:X}, 0x{:02X}", x, nn),
+            Self::Display { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Self::SkipIfKey { x } => write!(f, "SKP V{:X}", x),
+            Self::SkipIfNotKey { x } => write!(f, "SKNP V{:X}", x),
+            Self::SetVxToDelaytmr { x } => write!(f, "LD V{:X}, DT", x),
+            Self::GetKey { x } => write!(f, "LD V{:X}, K", x),
+            Self::SetDelaytmrToVx { x } => write!(f, "LD DT, V{:X}", x),
+            Self::SetSoundtmrToVx { x } => write!(f, "LD ST, V{:X}", x),
+            Self::AddToIndex { x } => write!(f, "ADD I, V{:X}", x),
+            Self::FontCharacter { x } => write!(f, "LD F, V{:X}", x),
+            Self::BinaryDecimalConversion { x } => write!(f, "LD B, V{:X}", x),
+            Self::StoreMemory { x } => write!(f, "LD [I], V{:X}", x),
+            Self::LoadMemory { x } => write!(f, "LD V{:X}, [I]", x),
+        }
+    }
+}
+
+#[test]
+fn test_decode() {
+    assert_eq!(decode(0x00e0).unwrap(), Instruction::ClearScreen);
+    assert_eq!(
+        decode(0xd12f).unwrap(),
+        Instruction::Display {
+            x: 0x1,
+            y: 0x2,
+            n: 0xf
+        }
+    );
+    assert_eq!(decode(0x8ab6).unwrap().to_string(), "SHR VA, VB");
+    assert_eq!(decode(0xf233).unwrap().to_string(), "LD B, V2");
+    assert!(matches!(decode(0x0000), Err(EmulationError::VacantMemory)));
+    assert!(matches!(
+        decode(0x8ab9),
+        Err(EmulationError::UnknownInstruction)
+    ));
+    // only 5XY0 is a skip, see `Quirks::skip_ignores_n`
+    assert!(decode(0x5ab3).is_err());
+}
+
+#[test]
+fn test_pattern_for() {
+    assert_eq!(pattern_for(0xf233), Some("FX33"));
+    assert_eq!(pattern_for(0xd125), Some("DXYN"));
+    assert_eq!(pattern_for(0xd120), Some("DXY0"));
+    assert_eq!(pattern_for(0x00e0), Some("00E0"));
+    assert_eq!(pattern_for(0x8ab9), None);
+}
+
+#[test]
+fn test_supported_opcodes() {
+    let opcodes = supported_opcodes();
+    let find = |pattern: &str| {
+        opcodes
+            .iter()
+            .find(|info| info.pattern == pattern)
+            .unwrap()
+            .implemented
+    };
+    assert!(find("FX33"));
+    assert!(find("00FF"));
+    assert!(!find("F000"));
+    // XO-CHIP's SAVE and LOAD share the 5 with SE VX, VY
+    assert!(!find("5XY2") && !find("5XY3"));
+    assert!(opcodes
+        .iter()
+        .filter(|info| info.variant == "CHIP-8")
+        .all(|info| info.implemented));
+}
+
+#[test]
+fn test_variant_for() {
+    assert_eq!(variant_for(0xd015), Some("CHIP-8"));
+    assert_eq!(variant_for(0xd010), Some("SUPER-CHIP"));
+    assert_eq!(variant_for(0xf000), Some("XO-CHIP"));
+    assert_eq!(variant_for(0x8abc), None);
+}
+
+#[test]
+fn test_describe() {
+    assert_eq!(
+        decode(0x7305).unwrap().describe(),
+        "V3 += 0x05, VF is left alone"
+    );
+    assert_eq!(
+        decode(0xd125).unwrap().describe(),
+        "draw the 5 row sprite at I at (V1, V2), VF = 1 on a collision"
+    );
+    assert_eq!(decode(0x1234).unwrap().describe(), "jump to 0x234");
+}
@@ -0,0 +1,199 @@
+// which physical keys make up the CHIP-8 keypad.
+// the default puts the keypad on the left side of a QWERTY keyboard,
+// and per-ROM configs can move any of the keys somewhere else.
+// gamepads get a handful of the keys too, for playing from the couch
+
+use ggez::input::gamepad::gilrs::Button;
+
+/// Scancode and gamepad button for each of the 16 CHIP-8 keys, indexed by the key.
+/// Only some keys have a button, a gamepad doesn't have 16 of them to spare.
+pub struct Keymap {
+    scancodes: [u32; 16],
+    buttons: [Option<Button>; 16],
+}
+
+/// scancodes of the 4 x 4 block of keys on the left of a QWERTY keyboard, row by row
+// all scancodes taken from
+// https://www.win.tue.nl/~aeb/linux/kbd/scancodes-1.html
+// since the URL has "linux" as a directory, I'm concerned if this works the same on windows
+// we will check this out later but it all works on my machine
+// MacOS can suffer (I don't have an accessible mac)
+const QWERTY_GRID: [[u32; 4]; 4] = [
+    [0x02, 0x03, 0x04, 0x05], // 1 2 3 4
+    [0x10, 0x11, 0x12, 0x13], // Q W E R
+    [0x1e, 0x1f, 0x20, 0x21], // A S D F
+    [0x2c, 0x2d, 0x2e, 0x2f], // Z X C V
+];
+
+/// the keypad of the COSMAC VIP, which most CHIP-8 ROMs were written for
+const COSMAC_VIP: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xc],
+    [0x4, 0x5, 0x6, 0xd],
+    [0x7, 0x8, 0x9, 0xe],
+    [0xa, 0x0, 0xb, 0xf],
+];
+
+/// the keypad of the DREAM 6800, counting up from the bottom left
+const DREAM_6800: [[usize; 4]; 4] = [
+    [0xc, 0xd, 0xe, 0xf],
+    [0x8, 0x9, 0xa, 0xb],
+    [0x4, 0x5, 0x6, 0x7],
+    [0x0, 0x1, 0x2, 0x3],
+];
+
+/// the keypad of the ETI-660, counting up each column from the bottom left
+const ETI_660: [[usize; 4]; 4] = [
+    [0x3, 0x7, 0xb, 0xf],
+    [0x2, 0x6, 0xa, 0xe],
+    [0x1, 0x5, 0x9, 0xd],
+    [0x0, 0x4, 0x8, 0xc],
+];
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::from_grid(&COSMAC_VIP)
+    }
+}
+
+impl Keymap {
+    /// puts the keys of a machine's keypad, given row by row, on the QWERTY block
+    fn from_grid(keypad: &[[usize; 4]; 4]) -> Self {
+        let mut scancodes = [0; 16];
+        for (keys, codes) in keypad.iter().zip(QWERTY_GRID) {
+            for (key, code) in keys.iter().zip(codes) {
+                scancodes[*key] = code;
+            }
+        }
+
+        // the d-pad is where WASD would be on the COSMAC VIP, and the face buttons
+        // take the keys around it that games tend to use for actions.
+        // These stay on the same CHIP-8 keys with every layout, since that's what games check for
+        let mut buttons = [None; 16];
+        buttons[0x5] = Some(Button::DPadUp);
+        buttons[0x7] = Some(Button::DPadLeft);
+        buttons[0x8] = Some(Button::DPadDown);
+        buttons[0x9] = Some(Button::DPadRight);
+        buttons[0x6] = Some(Button::South);
+        buttons[0x4] = Some(Button::East);
+        buttons[0xa] = Some(Button::West);
+        buttons[0xb] = Some(Button::North);
+
+        Keymap { scancodes, buttons }
+    }
+
+    /// looks up a machine's keypad layout by the name used on the command line
+    pub fn layout(name: &str) -> Option<Self> {
+        match name {
+            "cosmac_vip" => Some(Keymap::from_grid(&COSMAC_VIP)),
+            "dream6800" => Some(Keymap::from_grid(&DREAM_6800)),
+            "eti660" => Some(Keymap::from_grid(&ETI_660)),
+            _ => None,
+        }
+    }
+
+    /// moves CHIP-8 key `key` to the physical key at `scancode`,
+    /// swapping places with whichever key was there so no physical key presses two
+    pub fn set(&mut self, key: usize, scancode: u32) {
+        if let Some(other) = self.keypad_index(scancode) {
+            self.scancodes[other] = self.scancodes[key];
+        }
+        self.scancodes[key] = scancode;
+    }
+
+    /// finds the CHIP-8 key that sits at the position of a physical key,
+    /// or `None` if that key isn't part of the keypad
+    pub fn keypad_index(&self, scancode: u32) -> Option<usize> {
+        self.scancodes.iter().position(|code| *code == scancode)
+    }
+
+    /// moves CHIP-8 key `key` to a gamepad button, taking it away from whichever key had it
+    pub fn set_button(&mut self, key: usize, button: Button) {
+        for mapped in self.buttons.iter_mut() {
+            if *mapped == Some(button) {
+                *mapped = None;
+            }
+        }
+        self.buttons[key] = Some(button);
+    }
+
+    /// finds the CHIP-8 key a gamepad button presses,
+    /// or `None` if the button isn't mapped to one
+    pub fn gamepad_index(&self, button: Button) -> Option<usize> {
+        self.buttons
+            .iter()
+            .position(|mapped| *mapped == Some(button))
+    }
+}
+
+/// looks up a gamepad button by the name used in per-ROM configs
+pub fn button_named(name: &str) -> Option<Button> {
+    let button = match name {
+        "up" => Button::DPadUp,
+        "down" => Button::DPadDown,
+        "left" => Button::DPadLeft,
+        "right" => Button::DPadRight,
+        "south" => Button::South,
+        "east" => Button::East,
+        "west" => Button::West,
+        "north" => Button::North,
+        "left_bumper" => Button::LeftTrigger,
+        "right_bumper" => Button::RightTrigger,
+        "left_trigger" => Button::LeftTrigger2,
+        "right_trigger" => Button::RightTrigger2,
+        "select" => Button::Select,
+        "start" => Button::Start,
+        _ => return None,
+    };
+    Some(button)
+}
+
+#[test]
+fn test_keymap_remap() {
+    let mut keymap = Keymap::default();
+    assert_eq!(keymap.keypad_index(0x10), Some(0x4));
+    assert_eq!(keymap.keypad_index(0x39), None);
+
+    keymap.set(0x4, 0x39);
+    assert_eq!(keymap.keypad_index(0x39), Some(0x4));
+    assert_eq!(keymap.keypad_index(0x10), None);
+
+    // moving onto a key that's already used swaps the two
+    assert_eq!(keymap.keypad_index(0x02), Some(0x1));
+    keymap.set(0x5, 0x02);
+    assert_eq!(keymap.keypad_index(0x02), Some(0x5));
+    assert_eq!(keymap.keypad_index(0x11), Some(0x1)); // where 5 was
+}
+
+#[test]
+fn test_gamepad_remap() {
+    let mut keymap = Keymap::default();
+    assert_eq!(keymap.gamepad_index(Button::DPadUp), Some(0x5));
+    assert_eq!(keymap.gamepad_index(Button::Start), None);
+
+    keymap.set_button(0x2, button_named("up").unwrap());
+    assert_eq!(keymap.gamepad_index(Button::DPadUp), Some(0x2));
+    keymap.set_button(0x5, Button::Start);
+    assert_eq!(keymap.gamepad_index(Button::Start), Some(0x5));
+    assert!(button_named("turbo").is_none());
+}
+
+#[test]
+fn test_keymap_layouts() {
+    // the 1 and X keys, at the top left and bottom of the QWERTY block
+    let keys = |name: &str| {
+        let keymap = Keymap::layout(name).unwrap();
+        (keymap.keypad_index(0x02), keymap.keypad_index(0x2d))
+    };
+    assert_eq!(keys("cosmac_vip"), (Some(0x1), Some(0x0)));
+    assert_eq!(keys("dream6800"), (Some(0xc), Some(0x1)));
+    assert_eq!(keys("eti660"), (Some(0x3), Some(0x4)));
+    assert!(Keymap::layout("qwerty").is_none());
+
+    // every layout still has all 16 keys
+    for name in ["cosmac_vip", "dream6800", "eti660"] {
+        let keymap = Keymap::layout(name).unwrap();
+        for code in QWERTY_GRID.iter().flatten() {
+            assert!(keymap.keypad_index(*code).is_some());
+        }
+    }
+}
@@ -0,0 +1,6 @@
+// the emulator core lives in this library so it can be used
+// (and tested) without the ggez frontend in main.rs
+pub mod clock;
+pub mod emu;
+pub mod instruction;
+pub mod renderer;
@@ -1,42 +1,270 @@
 use ggez::conf::{WindowMode, WindowSetup};
 use ggez::event::{self, EventHandler};
-use ggez::graphics::{self, Canvas, Color, DrawParam};
+use ggez::graphics::Color;
+use ggez::input::gamepad::{gilrs::Button, GamepadId};
 use ggez::input::keyboard::{self, KeyInput};
 use ggez::{Context, ContextBuilder, GameResult};
 
+use std::env::args;
 use std::fs::read;
-use std::io::stdin;
+use std::io::{stdin, Read};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::time::{Duration, SystemTime};
 
-mod emu;
-use emu::{Emu, EmulationError};
+mod analysis;
+mod beep;
+mod capture;
+mod cli;
+mod config;
+mod ggez_renderer;
+mod keymap;
+mod recovery;
+mod repeat;
+mod repl;
+mod rominfo;
+mod smoke;
+use analysis::Analysis;
+use beep::Beeper;
+use capture::{dump_screen, Capture};
+use cli::{Options, DEFAULT_MAX_CATCH_UP};
+use config::Config;
+use ggez_renderer::{GgezRenderer, SCALE};
+use keymap::Keymap;
+use recovery::{ErrorPolicy, Recovery};
+use repeat::KeyRepeat;
+use rite_emu::clock::{frames_within, Clock, FrameTimer, InstructionBudget};
+use rite_emu::emu::{self, Emu};
+use rite_emu::instruction::decode;
+use rite_emu::renderer;
+use smoke::SmokeReport;
+
+const DESIRED_FPS: u32 = 60;
+const DEFAULT_CYCLES_PER_FRAME: u32 = 11; // 10-12 instructions per frame at 60 FPS
+const ERROR_HISTORY: usize = 16; // instructions to print leading up to an emulation error
+
+/// builds a fresh emulator with the ROM loaded, set up from the command line and config
+type MakeEmulator = dyn Fn(&[u8]) -> Result<Emu, emu::EmulationError>;
 
 // this file essentially comes from the ggez template
 // look there if you want more explanation for what all these things do
 // otherwise you can check stuff out with intellisense
 
 fn main() {
+    let mut options = match Options::parse(args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            println!("{}", message);
+            exit(1);
+        }
+    };
+
+    // get filepath for ROM, asking for it if it wasn't given as an argument
+    let filepath = match options.rom_path.take() {
+        Some(filepath) => filepath,
+        None => {
+            println!("relative path to ROM: ");
+            let mut filepath = String::new();
+            stdin()
+                .read_line(&mut filepath)
+                .expect("failed to read line");
+            filepath.trim().to_string()
+        }
+    };
+    // get ROM data, from a pipe if the path is -
+    let from_stdin = filepath == "-";
+    let rom = if from_stdin {
+        let mut rom = vec![];
+        stdin()
+            .lock()
+            .read_to_end(&mut rom)
+            .expect("Error reading the ROM from stdin");
+        if rom.is_empty() {
+            println!("no ROM was given on stdin");
+            exit(1);
+        }
+        rom
+    } else {
+        read(&filepath).expect("Error reading the given ROM filepath")
+    };
+
+    // the reports look at the ROM without running it
+    if options.info || options.analyze || options.disasm {
+        let analysis = Analysis::new(&rom);
+        let report = match (options.analyze, options.disasm, options.json) {
+            (true, _, false) => analysis.analysis_text(),
+            (true, _, true) => analysis.analysis_json().to_string(),
+            (_, true, false) => analysis.disassembly_text(),
+            (_, true, true) => analysis.disassembly_json().to_string(),
+            (_, _, false) => analysis.info_text(),
+            (_, _, true) => analysis.info_json().to_string(),
+        };
+        println!("{}", report);
+        return;
+    }
+
+    if options.listing {
+        let rom_len = rom.len();
+        let mut emulator = Emu::builder().xo_chip(options.xo_chip).build();
+        if let Err(e) = emulator.read_rom(rom) {
+            println!("{}", e);
+            exit(1);
+        }
+        print!(
+            "{}",
+            // a freshly loaded ROM has PC at its start
+            emulator.disassemble_annotated(emulator.pc(), rom_len.div_ceil(2))
+        );
+        return;
+    }
+
+    // settings from the command line win over the ones saved for this ROM
+    // (a piped ROM has no file for one to sit next to)
+    let config = if from_stdin {
+        Config::default()
+    } else {
+        Config::load(Path::new(&filepath))
+    };
+
+    // always run with a known seed, so any run can be replayed
+    let seed = options.seed.unwrap_or_else(rand::random);
+    println!("random seed: {}", seed);
+
+    // kept around so the ROM can be started over exactly the same way
+    let quirks = options.quirks.or(config.quirks).unwrap_or_default();
+    let make_emulator = move |rom: &[u8]| -> Result<Emu, emu::EmulationError> {
+        let mut builder = Emu::builder().xo_chip(options.xo_chip).quirks(quirks);
+        if let Some(platform) = options.platform {
+            builder = builder.platform(platform);
+        }
+        let mut emulator = builder.build();
+        emulator.read_rom(rom.to_vec())?;
+        if let Some(behavior) = options.zero_opcode_behavior {
+            emulator.quirks.zero_opcode_behavior = behavior;
+        }
+        emulator.seed_rng(seed);
+        for target in &options.watches {
+            emulator.add_watch(*target);
+        }
+        emulator.detect_self_modifying(options.detect_self_modifying);
+        emulator.log_pixel_changes(options.log_pixels);
+        emulator.lint(options.lint);
+        emulator.set_draw_mode(options.draw_mode);
+        emulator.keep_history(ERROR_HISTORY);
+        for pattern in &options.denied {
+            emulator.deny(pattern);
+        }
+        Ok(emulator)
+    };
+    let emulator = match make_emulator(&rom) {
+        Ok(emulator) => emulator,
+        Err(e) => {
+            println!("{}", e);
+            exit(1);
+        }
+    };
+    let cycles_per_frame = options
+        .cycles_per_frame
+        .or(config.cycles_per_frame)
+        .unwrap_or(DEFAULT_CYCLES_PER_FRAME);
+
+    if let Some(seconds) = options.smoke {
+        let mut emulator = emulator;
+        let report = SmokeReport::run(&mut emulator, seconds, cycles_per_frame);
+        println!("{}", report);
+        if let Some(path) = &options.dump_screen {
+            save_screen_dump(path, &emulator);
+        }
+        exit(if report.passed() { 0 } else { 1 });
+    }
+
+    if options.repl {
+        let mut emulator = emulator;
+        repl::run(&mut emulator);
+        if let Some(path) = &options.dump_screen {
+            save_screen_dump(path, &emulator);
+        }
+        return;
+    }
+
     // CHIP-8s use a 32 x 64 pixel screen!
-    let width = 64;
-    let height = 32;
+    // the window stays this size, hires pixels just get drawn smaller
+    // (and it's 64 x 32 for a display turned on its side)
+    let (width, height) = emulator.dimensions();
+    let (width, height) = options.orientation.dimensions(width, height);
+    let scale = SCALE * 64. / width.max(height) as f32;
 
     // Make a Context...
     let cb = ContextBuilder::new("Rite", "ash")
         .window_setup(WindowSetup::default().title("rite-emu"))
-        .window_mode(WindowMode::default().dimensions((width * 15) as f32, (height * 15) as f32));
+        .window_mode(WindowMode::default().dimensions(width as f32 * scale, height as f32 * scale));
 
     let (mut ctx, event_loop) = cb.build().expect("guh, could not create ggez context.");
 
-    // get filepath for ROM
-    println!("relative path to ROM: ");
-    let mut filepath = String::new();
-    stdin()
-        .read_line(&mut filepath)
-        .expect("failed to read line");
-    filepath = filepath.trim().to_string();
-    // get ROM data
-    let rom = read(filepath).expect("Error reading the given ROM filepath");
-
-    let state = MainState::new(&mut ctx, rom).expect("Error reading the given ROM filepath");
+    let mut state = MainState::new(&mut ctx, emulator);
+    state.on_error = options.on_error;
+    state.make_emulator = Box::new(make_emulator);
+    if options.watch {
+        if from_stdin {
+            println!("--watch needs a ROM file, not stdin, so it's being ignored");
+        } else {
+            state.rom_watcher = Some(RomWatcher::new(PathBuf::from(&filepath)));
+        }
+    }
+    state.rom = rom;
+    // whichever of the two catch-up limits is tighter wins
+    let max_catch_up = match (options.max_catch_up, options.max_instructions_per_update) {
+        (frames, None) => frames,
+        (None, Some(instructions)) => Some(frames_within(instructions, cycles_per_frame)),
+        (Some(frames), Some(instructions)) => {
+            Some(frames.min(frames_within(instructions, cycles_per_frame)))
+        }
+    };
+    state.frame_timer = FrameTimer::new(DESIRED_FPS, max_catch_up);
+    state.budget = InstructionBudget::new(cycles_per_frame, options.adaptive_cycles);
+    if let Some(ms) = options.slow {
+        // frames still go by at 60 Hz for the timers and keys, the clock only hands out instructions.
+        // After a hitch it's held to the same catch-up limits, counted in its own instructions
+        state.slow = Some(Clock::every(Duration::from_millis(ms)));
+        state.slow_max = match (options.max_instructions_per_update, max_catch_up) {
+            (Some(instructions), _) => instructions,
+            (None, Some(frames)) => {
+                (frames as u64 * 1000).div_ceil((DESIRED_FPS as u64).saturating_mul(ms)) as u32
+            }
+            (None, None) => u32::MAX,
+        }
+        .max(1);
+    }
+    state.blank_screen_hint = options.blank_screen_hint;
+    state.dump_screen = options.dump_screen.clone();
+    state.renderer.scanlines = options.scanlines;
+    state.renderer.orientation = options.orientation;
+    if let Some([r, g, b]) = config.colors.on {
+        state.renderer.on_color = Color::from_rgb(r, g, b);
+    }
+    if let Some([r, g, b]) = config.colors.off {
+        state.renderer.off_color = Color::from_rgb(r, g, b);
+    }
+    if let Some(layout) = options.layout {
+        state.keymap = layout;
+    }
+    for (key, scancode) in config.keymap {
+        state.keymap.set(key, scancode);
+    }
+    for (key, button) in config.gamepad {
+        state.keymap.set_button(key, button);
+    }
+    if options.sticky_keys {
+        state.sticky_keys = Some(StickyKeys::new(options.sticky_release));
+    }
+    if let Some((delay, rate)) = options.key_repeat {
+        state.key_repeat = Some(KeyRepeat::new(delay, rate));
+    }
+    if let Some(dir) = options.capture_dir {
+        let capture = Capture::new(&dir, options.capture_every)
+            .expect("Error creating the capture directory");
+        state.capture = Some(capture);
+    }
 
     // Run!
     event::run(ctx, event_loop, state);
@@ -44,10 +272,28 @@ fn main() {
 
 struct MainState {
     emulator: emu::Emu,
+    sticky_keys: Option<StickyKeys>, // only present when sticky keys are turned on
+    key_repeat: Option<KeyRepeat>,   // only present when held keys should repeat
+    capture: Option<Capture>,        // only present when capturing frames
+    beeper: Beeper,
+    frame_timer: FrameTimer, // how many 60 FPS frames need to run each update
+    budget: InstructionBudget, // how many instructions the next frame runs
+    slow: Option<Clock>,     // hands out instructions at the `--slow` speed instead of `budget`
+    slow_owed: u32,          // instructions the slow clock has handed out that haven't run yet
+    slow_max: u32,           // most instructions the slow clock can have owed at once
+    renderer: GgezRenderer,
+    keymap: Keymap,
+    on_error: ErrorPolicy,
+    paused: bool, // stopped at an emulation error, see `ErrorPolicy::Pause`
+    rom: Vec<u8>, // the ROM that's running, kept for resets
+    make_emulator: Box<MakeEmulator>, // builds the emulator from scratch again for resets
+    rom_watcher: Option<RomWatcher>, // only present when reloading the ROM as it changes, see `--watch`
+    blank_screen_hint: Option<u64>, // frames to wait for a first draw, `None` once the hint is shown
+    dump_screen: Option<PathBuf>,   // where the last frame gets written on exit
 }
 
 impl MainState {
-    pub fn new(_ctx: &mut Context, rom: Vec<u8>) -> Result<MainState, EmulationError> {
+    pub fn new(ctx: &mut Context, emulator: Emu) -> MainState {
         /*
         let mut squares: Vec<Mesh> = vec![];
         for i in 0..32 {
@@ -70,148 +316,435 @@ impl MainState {
         }
         */
 
-        let mut emulator = Emu::new();
-        emulator.read_rom(rom)?;
+        MainState {
+            emulator,
+            sticky_keys: None,
+            key_repeat: None,
+            capture: None,
+            beeper: Beeper::new(ctx),
+            frame_timer: FrameTimer::new(DESIRED_FPS, Some(DEFAULT_MAX_CATCH_UP)),
+            budget: InstructionBudget::new(DEFAULT_CYCLES_PER_FRAME, false),
+            slow: None,
+            slow_owed: 0,
+            slow_max: u32::MAX,
+            renderer: GgezRenderer::new(),
+            keymap: Keymap::default(),
+            on_error: ErrorPolicy::default(),
+            paused: false,
+            rom: vec![],
+            make_emulator: Box::new(Emu::from_rom),
+            rom_watcher: None,
+            blank_screen_hint: None,
+            dump_screen: None,
+        }
+    }
+
+    /// runs one frame's worth of instructions and hands the result to the renderer
+    fn run_frame(&mut self, ctx: &mut Context) {
+        let cycles = match self.slow {
+            Some(_) => self.slow_owed,
+            None => self.budget.next_frame(),
+        };
+        let result = renderer::run_frame(&mut self.emulator, cycles, &mut self.renderer);
+        if let Ok(ran) = &result {
+            match self.slow {
+                // whatever a display wait or key wait held back runs next frame
+                Some(_) => self.slow_owed -= ran,
+                None => self.budget.spend(*ran),
+            }
+        }
+        match self.on_error.recover(result, &self.emulator) {
+            Recovery::Continue => (),
+            Recovery::Quit(message) => {
+                println!("!ENCOUNTERED EMULATION ERROR!\n{}", message);
+                self.print_history();
+                ctx.request_quit();
+            }
+            Recovery::Pause(message) => {
+                println!(
+                    "!ENCOUNTERED EMULATION ERROR!\n{}\npaused, R resets",
+                    message
+                );
+                self.print_history();
+                self.renderer.overlay = Some(message);
+                self.paused = true;
+            }
+            Recovery::Reset(message) => {
+                println!("!ENCOUNTERED EMULATION ERROR!\n{}\nresetting", message);
+                self.print_history();
+                self.reset();
+            }
+        }
+        for hit in self.emulator.take_watch_hits() {
+            println!(
+                "watch: {:?} changed from 0x{:02X} to 0x{:02X} at 0x{:03X}",
+                hit.target, hit.old, hit.new, hit.pc
+            );
+        }
+        for hit in self.emulator.take_self_modify_hits() {
+            println!(
+                "self-modifying code: 0x{:03X} wrote to 0x{:03X}",
+                hit.pc, hit.address
+            );
+        }
+        for warning in self.emulator.take_warnings() {
+            println!("warning: {}", warning);
+        }
+        for change in self.emulator.take_pixel_changes() {
+            println!(
+                "pixel: ({}, {}) turned {} at 0x{:03X}",
+                change.x,
+                change.y,
+                if change.lit { "on" } else { "off" },
+                change.pc
+            );
+        }
+        if let Some(frames) = self.blank_screen_hint {
+            if self.emulator.never_drew_after(frames) {
+                println!(
+                    "nothing has been drawn after {} frames. \
+                    The ROM might be for another variant (try --xo-chip), \
+                    need other quirks (try --quirks), \
+                    or not be a CHIP-8 program at all (try --disasm)",
+                    frames
+                );
+                self.blank_screen_hint = None;
+            }
+        }
+        if self.emulator.is_halted() {
+            println!("ROM halted\n{}", self.emulator.status_line());
+            ctx.request_quit();
+        }
+
+        if let Some(sticky) = &mut self.sticky_keys {
+            for key in sticky.tick() {
+                self.emulator.keyrelease(key);
+            }
+        }
+        if let Some(repeat) = &mut self.key_repeat {
+            for (key, down) in repeat.tick() {
+                if down {
+                    self.emulator.keypress(key);
+                } else {
+                    self.emulator.keyrelease(key);
+                }
+            }
+        }
+    }
+
+    /// prints the instructions that ran right before now, the last one first
+    fn print_history(&self) {
+        println!("last instructions run, most recent first:");
+        for (address, opcode) in self.emulator.recent_history().into_iter().rev() {
+            match decode(opcode) {
+                Ok(instruction) => println!("0x{:03X}  {:04X}  {}", address, opcode, instruction),
+                Err(_) => println!("0x{:03X}  {:04X}  ???", address, opcode),
+            }
+        }
+    }
+
+    /// starts the ROM over from the beginning, unpausing if paused
+    fn reset(&mut self) {
+        // the same ROM loaded fine the first time
+        let emulator =
+            (self.make_emulator)(&self.rom).expect("Error reading the given ROM filepath");
+        self.restart(emulator);
+    }
+
+    /// swaps in a new version of the ROM and starts it from the beginning,
+    /// keeping the old one running if the new one can't be loaded
+    fn reload(&mut self, rom: Vec<u8>) {
+        match (self.make_emulator)(&rom) {
+            Ok(emulator) => {
+                println!("reloaded the ROM ({} bytes)", rom.len());
+                self.rom = rom;
+                self.restart(emulator);
+            }
+            Err(e) => println!("couldn't reload the ROM: {}", e),
+        }
+    }
 
-        Ok(MainState { emulator })
+    /// carries on with a freshly built `emulator`, letting go of anything left over from the old one
+    fn restart(&mut self, emulator: Emu) {
+        self.emulator = emulator;
+        self.paused = false;
+        self.renderer.overlay = None;
+        if let Some(sticky) = &mut self.sticky_keys {
+            *sticky = StickyKeys::new(sticky.release_after);
+        }
+        if let Some(repeat) = &mut self.key_repeat {
+            repeat.clear();
+        }
+    }
+
+    /// a key or button for CHIP-8 key `key` went down
+    fn press_key(&mut self, key: usize, repeated: bool) {
+        match &mut self.sticky_keys {
+            // holding a key down for a while shouldn't keep flipping it
+            Some(sticky) if !repeated => {
+                if sticky.toggle(key) {
+                    self.emulator.keypress(key);
+                } else {
+                    self.emulator.keyrelease(key);
+                }
+            }
+            Some(_) => (),
+            None => {
+                if let Some(repeat) = &mut self.key_repeat {
+                    // the repeating is up to us, not the OS
+                    if repeated {
+                        return;
+                    }
+                    repeat.press(key);
+                }
+                self.emulator.keypress(key);
+            }
+        }
+    }
+
+    /// a key or button for CHIP-8 key `key` went up
+    fn release_key(&mut self, key: usize) {
+        // sticky keys only let go when toggled or timed out
+        if self.sticky_keys.is_none() {
+            if let Some(repeat) = &mut self.key_repeat {
+                repeat.release(key);
+            }
+            self.emulator.keyrelease(key);
+        }
     }
 }
 
 impl EventHandler for MainState {
+    #[allow(deprecated)] // captures borrow the screen instead of copying it every frame
     fn update(&mut self, ctx: &mut Context) -> GameResult {
+        if let Some(rom) = self.rom_watcher.as_mut().and_then(RomWatcher::changed) {
+            self.reload(rom);
+        }
         // Something here about doing so many instructions per frame
         // utilize a TimeContext for this
-        const DESIRED_FPS: u32 = 60;
-
-        while ctx.time.check_update_time(DESIRED_FPS) {
+        if let Some(clock) = &mut self.slow {
+            let released = clock.tick().min(u32::MAX as usize) as u32;
+            self.slow_owed = self.slow_owed.saturating_add(released).min(self.slow_max);
+        }
+        let frames = self.frame_timer.frames(ctx.time.delta());
+        for _frame in 0..frames {
             // check if we're on target for 60 fps
             // and if so, do the thing.
-            for _i in 0..11 {
-                // 10-12 instructions per frame at 60 FPS
-                if let Err(e) = self.emulator.fetch_decode_execute_instr() {
-                    println!("!ENCOUNTERED EMULATION ERROR!\n{}", e);
-                    ctx.request_quit();
+            // nothing runs while paused, on an error or with P
+            if self.paused || self.emulator.is_paused() {
+                break;
+            }
+            // when capturing, frames only run when asked for
+            if let Some(capture) = &mut self.capture {
+                if !capture.should_advance() {
+                    continue;
                 }
-                self.emulator.decrement_delay();
-                self.emulator.decrement_sound();
             }
-        }
-        Ok(())
-    }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let mut canvas = Canvas::from_frame(ctx, Color::WHITE);
-        for y in 0..32 {
-            for x in 0..64 {
-                let pixel_index = (x + (y * 64)) as usize;
-                let x = (x * 15) as f32;
-                let y = (y * 15) as f32;
-                if self.emulator.pixels[pixel_index] {
-                    // pixel is turned on
-                    canvas.draw(
-                        &graphics::Quad,
-                        DrawParam::default()
-                            .color(Color::WHITE)
-                            .scale([15., 15.])
-                            .dest([x, y]),
-                    );
-                } else {
-                    // pixel is turned off
-                    canvas.draw(
-                        &graphics::Quad,
-                        DrawParam::default()
-                            .color(Color::BLACK)
-                            .scale([15., 15.])
-                            .dest([x, y]),
-                    );
+            self.run_frame(ctx);
+
+            if let Some(capture) = &mut self.capture {
+                if let Err(e) = capture.save_frame(
+                    self.emulator.pixels(),
+                    self.emulator.screen_width(),
+                    self.emulator.screen_height(),
+                ) {
+                    println!("couldn't save captured frame: {}", e);
                 }
             }
         }
+        self.beeper
+            .update(self.emulator.is_beeping() && !self.emulator.is_paused());
+        Ok(())
+    }
 
-        canvas.finish(ctx)?;
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        self.renderer.draw(ctx)?;
 
         ggez::timer::yield_now();
 
         Ok(())
     }
 
+    fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, ggez::GameError> {
+        // runs for errors and halts too, since those quit through `request_quit`
+        if let Some(path) = &self.dump_screen {
+            save_screen_dump(path, &self.emulator);
+        }
+        Ok(false)
+    }
+
     fn key_down_event(
         &mut self,
         ctx: &mut Context,
         input: keyboard::KeyInput,
-        _repeated: bool,
+        repeated: bool,
     ) -> Result<(), ggez::GameError> {
-        match input.scancode {
-            // all scancodes taken from
-            // https://www.win.tue.nl/~aeb/linux/kbd/scancodes-1.html
-            // since the URL has "linux" as a directory, I'm concerned if this works the same on windows
-            // we will check this out later but it all works on my machine
-            // MacOS can suffer (I don't have an accessible mac)
-            0x01 => ctx.request_quit(), // escape key
-
-            // first four correspond to 1 2 3 C on COSMAC VIP keypad layout
-            0x02 => self.emulator.keypress(0x1), // QWERTY position of 1 key
-            0x03 => self.emulator.keypress(0x2), // QWERTY position of 2 key
-            0x04 => self.emulator.keypress(0x3), // QWERTY position of 3 key
-            0x05 => self.emulator.keypress(0xc), // QWERTY position of 4 key
-
-            // second four correspond to 4 5 6 D on COSMAC VIP keypad layout
-            0x10 => self.emulator.keypress(0x4), // QWERTY position of Q key
-            0x11 => self.emulator.keypress(0x5), // QWERTY position of W key
-            0x12 => self.emulator.keypress(0x6), // QWERTY position of E key
-            0x13 => self.emulator.keypress(0xd), // QWERTY position of R key
-
-            // third four correspond to 7 8 9 E on COSMAC VIP keypad layout
-            0x1e => self.emulator.keypress(0x7), // QWERTY position of A key
-            0x1f => self.emulator.keypress(0x8), // QWERTY position of S key
-            0x20 => self.emulator.keypress(0x9), // QWERTY position of D key
-            0x21 => self.emulator.keypress(0xe), // QWERTY position of F key
-
-            // fourth four correspond to A 0 B F on COSMAC VIP keypad layout
-            0x2c => self.emulator.keypress(0xa), // QWERTY position of Z key
-            0x2d => self.emulator.keypress(0x0), // QWERTY position of X key
-            0x2e => self.emulator.keypress(0xb), // QWERTY position of C key
-            0x2f => self.emulator.keypress(0xf), // QWERTY position of V key
-            _ => (),
+        if input.scancode == 0x01 {
+            // escape key
+            ctx.request_quit();
+        }
+        if input.scancode == 0x13 && self.paused {
+            // R starts over after pausing on an error
+            self.reset();
+            return Ok(());
+        }
+        if input.scancode == 0x19 && !self.paused && !repeated {
+            // P pauses and resumes, keys pressed in between wait for resuming
+            let paused = !self.emulator.is_paused();
+            self.emulator.set_paused(paused);
+            self.renderer.overlay = paused.then(|| "paused, P resumes".to_string());
+            return Ok(());
+        }
+        if input.scancode == 0x39 {
+            // space bar steps one frame forward while capturing
+            if let Some(capture) = &mut self.capture {
+                capture.request_frame();
+            }
+        }
+
+        if let Some(key) = self.keymap.keypad_index(input.scancode) {
+            self.press_key(key, repeated);
         }
 
         Ok(())
     }
 
     fn key_up_event(&mut self, _ctx: &mut Context, input: KeyInput) -> Result<(), ggez::GameError> {
-        match input.scancode {
-            // all scancodes taken from
-            // https://www.win.tue.nl/~aeb/linux/kbd/scancodes-1.html
-            // since the URL has "linux" as a directory, I'm concerned if this works the same on windows
-            // we will check this out later but it all works on my machine
-            // MacOS can suffer (I don't have an accessible mac)
-
-            // first four correspond to 1 2 3 C on COSMAC VIP keypad layout
-            0x02 => self.emulator.keyrelease(0x1), // QWERTY position of 1 key
-            0x03 => self.emulator.keyrelease(0x2), // QWERTY position of 2 key
-            0x04 => self.emulator.keyrelease(0x3), // QWERTY position of 3 key
-            0x05 => self.emulator.keyrelease(0xc), // QWERTY position of 4 key
-
-            // second four correspond to 4 5 6 D on COSMAC VIP keypad layout
-            0x10 => self.emulator.keyrelease(0x4), // QWERTY position of Q key
-            0x11 => self.emulator.keyrelease(0x5), // QWERTY position of W key
-            0x12 => self.emulator.keyrelease(0x6), // QWERTY position of E key
-            0x13 => self.emulator.keyrelease(0xd), // QWERTY position of R key
-
-            // third four correspond to 7 8 9 E on COSMAC VIP keypad layout
-            0x1e => self.emulator.keyrelease(0x7), // QWERTY position of A key
-            0x1f => self.emulator.keyrelease(0x8), // QWERTY position of S key
-            0x20 => self.emulator.keyrelease(0x9), // QWERTY position of D key
-            0x21 => self.emulator.keyrelease(0xe), // QWERTY position of F key
-
-            // fourth four correspond to A 0 B F on COSMAC VIP keypad layout
-            0x2c => self.emulator.keyrelease(0xa), // QWERTY position of Z key
-            0x2d => self.emulator.keyrelease(0x0), // QWERTY position of X key
-            0x2e => self.emulator.keyrelease(0xb), // QWERTY position of C key
-            0x2f => self.emulator.keyrelease(0xf), // QWERTY position of V key
-            _ => (),
+        if let Some(key) = self.keymap.keypad_index(input.scancode) {
+            self.release_key(key);
+        }
+
+        Ok(())
+    }
+
+    // without a controller plugged in these just never get called
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: Button,
+        _id: GamepadId,
+    ) -> Result<(), ggez::GameError> {
+        if let Some(key) = self.keymap.gamepad_index(button) {
+            self.press_key(key, false);
         }
 
         Ok(())
     }
+
+    fn gamepad_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: Button,
+        _id: GamepadId,
+    ) -> Result<(), ggez::GameError> {
+        if let Some(key) = self.keymap.gamepad_index(button) {
+            self.release_key(key);
+        }
+
+        Ok(())
+    }
+
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) -> Result<(), ggez::GameError> {
+        // keys let go while another window has focus never send a key up,
+        // so everything gets let go up front instead of staying stuck down.
+        // Sticky keys were never physically held, so they stay how they were
+        if !gained && self.sticky_keys.is_none() {
+            if let Some(repeat) = &mut self.key_repeat {
+                repeat.clear();
+            }
+            self.emulator.release_all_keys();
+        }
+
+        Ok(())
+    }
+}
+
+/// Accessibility mode where a keypress toggles a key
+/// instead of it needing to be physically held down.
+///
+/// # Fields
+/// * `held_for` - frames each key has been held for, `None` if it isn't held
+/// * `release_after` - frames after which a held key lets go on its own, `None` if never
+struct StickyKeys {
+    held_for: [Option<u32>; 16],
+    release_after: Option<u32>,
+}
+
+impl StickyKeys {
+    fn new(release_after: Option<u32>) -> Self {
+        StickyKeys {
+            held_for: [None; 16],
+            release_after,
+        }
+    }
+
+    /// flips whether `key` is held, returning true if it's now held down
+    fn toggle(&mut self, key: usize) -> bool {
+        self.held_for[key] = match self.held_for[key] {
+            Some(_) => None,
+            None => Some(0),
+        };
+        self.held_for[key].is_some()
+    }
+
+    /// counts one frame for every held key,
+    /// releasing (and returning) the ones that ran out of time
+    fn tick(&mut self) -> Vec<usize> {
+        let mut released = vec![];
+        for (key, held_for) in self.held_for.iter_mut().enumerate() {
+            if let Some(frames) = held_for {
+                *frames += 1;
+                if self.release_after.is_some_and(|limit| *frames >= limit) {
+                    *held_for = None;
+                    released.push(key);
+                }
+            }
+        }
+        released
+    }
+}
+
+/// Notices when the ROM file gets written to, for `--watch`.
+///
+/// # Fields
+/// * `path` - the ROM file
+/// * `modified` - when the ROM was last modified as of the last look, `None` if that couldn't be found out
+struct RomWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl RomWatcher {
+    fn new(path: PathBuf) -> Self {
+        let modified = Self::modified(&path);
+        RomWatcher { path, modified }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        path.metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// the ROM's new contents if it changed since the last look.
+    /// While an assembler is in the middle of writing it, the file can be missing or empty
+    /// for a moment, so that's left alone until a later look finds the finished ROM
+    fn changed(&mut self) -> Option<Vec<u8>> {
+        let modified = Self::modified(&self.path)?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        let rom = read(&self.path).ok().filter(|rom| !rom.is_empty())?;
+        self.modified = Some(modified);
+        Some(rom)
+    }
+}
+
+/// writes the screen for `--dump-screen`, only complaining if that fails
+fn save_screen_dump(path: &Path, emulator: &Emu) {
+    match dump_screen(path, emulator) {
+        Ok(()) => println!("dumped the screen to {}", path.display()),
+        Err(e) => println!("couldn't dump the screen to {}: {}", path.display(), e),
+    }
 }
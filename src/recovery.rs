@@ -0,0 +1,67 @@
+// what the frontend does when a ROM runs into an emulation error.
+// quitting is the safe default, but when debugging it's nicer to stop
+// and look at what went wrong, or just start the ROM over
+
+use rite_emu::emu::{Emu, EmulationError};
+
+/// How to handle an `EmulationError` while running in a window.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ErrorPolicy {
+    #[default]
+    Quit, // close the window
+    Pause, // stop at the faulting instruction and show the error on screen
+    Reset, // start the ROM over from the beginning
+}
+
+/// What the frontend should do after running a frame.
+/// Everything but `Continue` carries a message describing the error.
+#[derive(Debug, PartialEq)]
+pub enum Recovery {
+    Continue,
+    Quit(String),
+    Pause(String),
+    Reset(String),
+}
+
+impl ErrorPolicy {
+    /// decides what to do about how a frame of `emulator` went
+    pub fn recover<T>(self, result: Result<T, EmulationError>, emulator: &Emu) -> Recovery {
+        let e = match result {
+            Ok(_) => return Recovery::Continue,
+            Err(e) => e,
+        };
+        let message = format!("{}\n{}", e, emulator.status_line());
+        match self {
+            Self::Quit => Recovery::Quit(message),
+            Self::Pause => Recovery::Pause(message),
+            Self::Reset => Recovery::Reset(message),
+        }
+    }
+}
+
+#[test]
+fn test_error_policy_dispatch() {
+    // 8XY9 isn't an instruction
+    let mut emulator = Emu::new();
+    emulator.read_rom(vec![0x60, 0x01, 0x80, 0x19]).unwrap();
+    assert_eq!(
+        ErrorPolicy::Pause.recover(emulator.step_frame(1), &emulator),
+        Recovery::Continue
+    );
+
+    let result = emulator.step_frame(1);
+    assert!(result.is_err());
+    let message = format!(
+        "ran into an unrecognized instruction\n{}",
+        emulator.status_line()
+    );
+    let recover = |policy: ErrorPolicy| {
+        policy.recover(Err::<(), _>(EmulationError::UnknownInstruction), &emulator)
+    };
+    assert_eq!(recover(ErrorPolicy::Quit), Recovery::Quit(message.clone()));
+    assert_eq!(
+        recover(ErrorPolicy::Pause),
+        Recovery::Pause(message.clone())
+    );
+    assert_eq!(recover(ErrorPolicy::Reset), Recovery::Reset(message));
+}
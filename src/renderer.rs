@@ -0,0 +1,254 @@
+// the emulator doesn't know or care what it's being shown on,
+// anything that can put a framebuffer somewhere can be a frontend
+
+use crate::emu::{Emu, EmulationError, HIRES_SIZE};
+
+/// Something that shows the emulator's screen, like a window or a terminal.
+pub trait Renderer {
+    /// shows `framebuffer`, which is `width` pixels wide and `height` tall,
+    /// with each row one after the other and `true` meaning lit
+    fn present(&mut self, framebuffer: &[bool], width: usize, height: usize);
+}
+
+/// runs one frame of `emulator` (up to `cycles` instructions),
+/// then hands whatever ended up on the screen to `renderer`.
+/// Returns how many instructions ran, like `Emu::step_frame`.
+///
+/// Only the screen as it is at the end of the frame gets presented, and only if it changed,
+/// so a frame with a thousand `DXYN`s still costs one `present`, and one
+/// that never touches the screen (waiting on a key, a busy loop, ...) costs none.
+/// The screen is presented even if an instruction failed, so the frame leading up
+/// to the error can still be seen.
+// borrowing the screen is all a renderer needs, no copy
+#[allow(deprecated)]
+pub fn run_frame<R: Renderer>(
+    emulator: &mut Emu,
+    cycles: u32,
+    renderer: &mut R,
+) -> Result<u32, EmulationError> {
+    let result = emulator.step_frame(cycles);
+    if emulator.take_dirty() {
+        let (width, height) = emulator.dimensions();
+        renderer.present(emulator.pixels(), width, height);
+    }
+    result
+}
+
+/// Wraps another renderer so it's always shown the 128 x 64 SUPER-CHIP screen,
+/// with each lores pixel as a 2 x 2 block, the way some SUPER-CHIP interpreters
+/// show lores ROMs on their hires display. Handy for frontends whose output
+/// is fixed to one size, like an LED matrix or a video recording.
+///
+/// # Fields
+/// * `inner` - the renderer frames get passed on to
+/// * `expanded` - the last lores frame blown up to hires, kept to avoid reallocating every frame
+pub struct HiresDisplay<R: Renderer> {
+    pub inner: R,
+    expanded: Vec<bool>,
+}
+
+impl<R: Renderer> HiresDisplay<R> {
+    pub fn new(inner: R) -> Self {
+        HiresDisplay {
+            inner,
+            expanded: vec![],
+        }
+    }
+}
+
+impl<R: Renderer> Renderer for HiresDisplay<R> {
+    fn present(&mut self, framebuffer: &[bool], width: usize, height: usize) {
+        let (hires_width, hires_height) = HIRES_SIZE;
+        let (scale_x, scale_y) = (hires_width / width, hires_height / height);
+        self.expanded.clear();
+        for y in 0..hires_height {
+            let row = y / scale_y * width;
+            self.expanded
+                .extend((0..hires_width).map(|x| framebuffer[row + x / scale_x]));
+        }
+        self.inner
+            .present(&self.expanded, hires_width, hires_height);
+    }
+}
+
+/// How far a display is turned clockwise from upright, see `Orientation`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Rotation {
+    #[default]
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+/// Which way up a display is mounted, for screens that are rotated or mirrored.
+/// Frontends apply it when showing a frame, the emulator's own framebuffer is left as it is.
+/// Flipping happens first, then the rotation.
+///
+/// # Fields
+/// * `flip_h` - mirror the screen left to right
+/// * `flip_v` - mirror the screen top to bottom
+/// * `rotation` - how far to turn the screen clockwise
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Orientation {
+    pub flip_h: bool,
+    pub flip_v: bool,
+    pub rotation: Rotation,
+}
+
+impl Orientation {
+    /// how big a `width` by `height` screen ends up, which is on its side for 90 and 270
+    pub fn dimensions(&self, width: usize, height: usize) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => (width, height),
+            Rotation::Rotate90 | Rotation::Rotate270 => (height, width),
+        }
+    }
+
+    /// where the pixel at (`x`, `y`) on a `width` by `height` screen ends up
+    pub fn map(&self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        let x = if self.flip_h { width - 1 - x } else { x };
+        let y = if self.flip_v { height - 1 - y } else { y };
+        match self.rotation {
+            Rotation::Rotate0 => (x, y),
+            Rotation::Rotate90 => (height - 1 - y, x),
+            Rotation::Rotate180 => (width - 1 - x, height - 1 - y),
+            Rotation::Rotate270 => (y, width - 1 - x),
+        }
+    }
+
+    /// `framebuffer`, which is `width` pixels wide and `height` tall, turned this way up.
+    /// The size it ends up is `dimensions`
+    pub fn apply(&self, framebuffer: &[bool], width: usize, height: usize) -> Vec<bool> {
+        let (new_width, new_height) = self.dimensions(width, height);
+        let mut oriented = vec![false; new_width * new_height];
+        for (index, _) in framebuffer.iter().enumerate().filter(|(_, lit)| **lit) {
+            let (x, y) = self.map(index % width, index / width, width, height);
+            oriented[x + y * new_width] = true;
+        }
+        oriented
+    }
+}
+
+// keeps everything it's asked to show, along with the size it was given
+#[cfg(test)]
+struct Recorder {
+    frames: Vec<(Vec<bool>, usize, usize)>,
+}
+
+#[cfg(test)]
+impl Renderer for Recorder {
+    fn present(&mut self, framebuffer: &[bool], width: usize, height: usize) {
+        self.frames.push((framebuffer.to_vec(), width, height));
+    }
+}
+
+#[test]
+fn test_renderer_presents_changed_frames() {
+    // draw the 0 glyph at the top left, then clear the screen, then loop forever
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0xa0, 0x50, 0xd0, 0x05, 0x00, 0xe0, 0x12, 0x06])
+        .unwrap();
+    let mut recorder = Recorder { frames: vec![] };
+    run_frame(&mut emulator, 2, &mut recorder).unwrap();
+    run_frame(&mut emulator, 1, &mut recorder).unwrap();
+    run_frame(&mut emulator, 5, &mut recorder).unwrap();
+
+    // the last frame only loops, so there's nothing new to show
+    assert_eq!(recorder.frames.len(), 2);
+    for (framebuffer, width, height) in &recorder.frames {
+        assert_eq!((*width, *height), (64, 32));
+        assert_eq!(framebuffer.len(), width * height);
+    }
+    let lit = |frame: usize| recorder.frames[frame].0.iter().filter(|on| **on).count();
+    assert_eq!(lit(0), 14); // the 0 glyph
+    assert_eq!(lit(1), 0);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_renderer_presents_once_per_frame() {
+    // draw the 0 glyph over and over, at a different spot each time
+    let mut emulator = Emu::new();
+    emulator
+        .read_rom(vec![0xa0, 0x50, 0xd0, 0x15, 0x71, 0x01, 0x12, 0x02])
+        .unwrap();
+    let mut recorder = Recorder { frames: vec![] };
+    for _ in 0..4 {
+        run_frame(&mut emulator, 300, &mut recorder).unwrap();
+    }
+    // a hundred draws a frame, but only the screen each frame ended on gets shown
+    assert_eq!(recorder.frames.len(), 4);
+    assert_eq!(recorder.frames[3].0, emulator.pixels());
+}
+
+#[test]
+fn test_hires_display() {
+    // one lit lores pixel at (3, 2)
+    let mut display = HiresDisplay::new(Recorder { frames: vec![] });
+    let mut lores = vec![false; 64 * 32];
+    lores[3 + 2 * 64] = true;
+    display.present(&lores, 64, 32);
+
+    let (framebuffer, width, height) = &display.inner.frames[0];
+    assert_eq!((*width, *height), HIRES_SIZE);
+    let lit: Vec<usize> = (0..framebuffer.len()).filter(|i| framebuffer[*i]).collect();
+    assert_eq!(
+        lit,
+        vec![6 + 4 * 128, 7 + 4 * 128, 6 + 5 * 128, 7 + 5 * 128]
+    );
+
+    // hires frames go through as they are
+    let mut hires = vec![false; 128 * 64];
+    hires[1] = true;
+    display.present(&hires, 128, 64);
+    assert_eq!(display.inner.frames[1].0, hires);
+}
+
+#[test]
+fn test_orientation() {
+    // one lit pixel at (3, 1) on the lores screen
+    let mut framebuffer = vec![false; 64 * 32];
+    framebuffer[3 + 64] = true;
+    let lit_at = |orientation: Orientation| {
+        let (width, height) = orientation.dimensions(64, 32);
+        let oriented = orientation.apply(&framebuffer, 64, 32);
+        assert_eq!(oriented.len(), width * height);
+        let lit: Vec<usize> = (0..oriented.len()).filter(|i| oriented[*i]).collect();
+        assert_eq!(lit.len(), 1);
+        ((lit[0] % width, lit[0] / width), (width, height))
+    };
+    let rotated = |rotation: Rotation| Orientation {
+        rotation,
+        ..Orientation::default()
+    };
+    assert_eq!(lit_at(Orientation::default()), ((3, 1), (64, 32)));
+    assert_eq!(lit_at(rotated(Rotation::Rotate90)), ((30, 3), (32, 64)));
+    assert_eq!(lit_at(rotated(Rotation::Rotate180)), ((60, 30), (64, 32)));
+    assert_eq!(lit_at(rotated(Rotation::Rotate270)), ((1, 60), (32, 64)));
+    let flip_h = Orientation {
+        flip_h: true,
+        ..Orientation::default()
+    };
+    assert_eq!(lit_at(flip_h), ((60, 1), (64, 32)));
+    let flip_v = Orientation {
+        flip_v: true,
+        ..Orientation::default()
+    };
+    assert_eq!(lit_at(flip_v), ((3, 30), (64, 32)));
+    // flipping both ways is the same as turning it around
+    let both = Orientation {
+        flip_h: true,
+        flip_v: true,
+        ..Orientation::default()
+    };
+    assert_eq!(lit_at(both), lit_at(rotated(Rotation::Rotate180)));
+    // flips happen before the rotation
+    let flipped_then_rotated = Orientation {
+        flip_h: true,
+        rotation: Rotation::Rotate90,
+        ..Orientation::default()
+    };
+    assert_eq!(lit_at(flipped_then_rotated), ((30, 60), (32, 64)));
+}
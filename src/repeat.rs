@@ -0,0 +1,87 @@
+// key repeat for games that only act when a key goes down.
+// most games look at whether a key is held every frame, and those are
+// happy with the key just staying down, but some wait for it to be let go
+// before they'll take it again, which makes scrolling through a menu tedious
+
+/// Turns a held key into a press, then after `delay` frames another press
+/// every `rate` frames, with the key let go in the frames between.
+///
+/// # Fields
+/// * `delay` - frames a key has to be held before it starts repeating
+/// * `rate` - frames between repeats
+/// * `held_for` - frames each key has been held for, `None` if it isn't held
+pub struct KeyRepeat {
+    delay: u32,
+    rate: u32,
+    held_for: [Option<u32>; 16],
+}
+
+impl KeyRepeat {
+    pub fn new(delay: u32, rate: u32) -> Self {
+        KeyRepeat {
+            delay,
+            rate: rate.max(1),
+            held_for: [None; 16],
+        }
+    }
+
+    /// `key` went down, and should be down for the frame that's coming up
+    pub fn press(&mut self, key: usize) {
+        self.held_for[key] = Some(0);
+    }
+
+    /// `key` was let go
+    pub fn release(&mut self, key: usize) {
+        self.held_for[key] = None;
+    }
+
+    /// lets go of every key, like after starting over
+    pub fn clear(&mut self) {
+        self.held_for = [None; 16];
+    }
+
+    /// counts one frame for every held key, returning each held key
+    /// along with whether it should be down for the next frame
+    pub fn tick(&mut self) -> Vec<(usize, bool)> {
+        let mut keys = vec![];
+        for (key, held_for) in self.held_for.iter_mut().enumerate() {
+            if let Some(frames) = held_for {
+                *frames += 1;
+                let down =
+                    *frames >= self.delay && (*frames - self.delay).is_multiple_of(self.rate);
+                keys.push((key, down));
+            }
+        }
+        keys
+    }
+}
+
+#[test]
+fn test_key_repeat_timing() {
+    // down on the press, then again after 4 frames, then every 2
+    let mut repeat = KeyRepeat::new(4, 2);
+    repeat.press(0x5);
+    let mut down = vec![true];
+    for _ in 0..9 {
+        let keys = repeat.tick();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].0, 0x5);
+        down.push(keys[0].1);
+    }
+    assert_eq!(
+        down,
+        [true, false, false, false, true, false, true, false, true, false]
+    );
+
+    // letting go stops it, and pressing again starts the delay over
+    repeat.release(0x5);
+    assert!(repeat.tick().is_empty());
+    repeat.press(0x5);
+    assert_eq!(repeat.tick(), vec![(0x5, false)]);
+
+    // a rate of 1 just keeps the key down once the delay is over
+    let mut repeat = KeyRepeat::new(2, 1);
+    repeat.press(0x0);
+    let down: Vec<bool> = (0..4).map(|_| repeat.tick()[0].1).collect();
+    assert_eq!(down, [false, true, true, true]);
+}
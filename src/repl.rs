@@ -0,0 +1,188 @@
+// a line based debugger for running ROMs without a window,
+// kind of like a very small gdb for CHIP-8
+
+use std::io::{stdin, stdout, BufRead, Write};
+
+use crate::cli::parse_number;
+use rite_emu::emu::Emu;
+
+const HELP: &str = "\
+commands:
+  step             run one instruction
+  run N            run N instructions, stopping early at a breakpoint
+  regs             show the registers
+  mem ADDR LEN     show LEN bytes of memory starting at ADDR
+  break ADDR       set a breakpoint at ADDR, or clear it if there already is one
+  disasm [ADDR] [COUNT]
+                   disassemble COUNT instructions (10 if not given) from ADDR (PC if not given)
+  help             show this
+  quit             exit";
+
+/// Something the user asked the REPL to do.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Step,
+    Run(usize),
+    Regs,
+    Mem { address: u16, len: usize },
+    Break(u16),
+    Disasm { address: Option<u16>, count: usize },
+    Help,
+    Quit,
+}
+
+/// parses one line of input into a `Command`,
+/// returning a message describing the problem if it doesn't make sense
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Err("type a command, or help for a list of them".to_string()),
+    };
+    let arguments: Vec<&str> = words.collect();
+
+    let number = |index: usize, name: &str| -> Result<u16, String> {
+        let text = arguments
+            .get(index)
+            .ok_or(format!("{} needs {}", command, name))?;
+        parse_number(text).ok_or(format!("{} isn't a number", text))
+    };
+
+    let parsed = match command {
+        "step" | "s" => Command::Step,
+        "run" | "r" => Command::Run(number(0, "a number of instructions")? as usize),
+        "regs" => Command::Regs,
+        "mem" => Command::Mem {
+            address: number(0, "an address")?,
+            len: number(1, "a length")? as usize,
+        },
+        "break" | "b" => Command::Break(number(0, "an address")?),
+        "disasm" | "d" => Command::Disasm {
+            address: arguments
+                .first()
+                .map(|_| number(0, "an address"))
+                .transpose()?,
+            count: arguments
+                .get(1)
+                .map(|_| number(1, "a count"))
+                .transpose()?
+                .unwrap_or(10) as usize,
+        },
+        "help" | "h" => Command::Help,
+        "quit" | "q" => Command::Quit,
+        _ => return Err(format!("unknown command {}, try help", command)),
+    };
+    Ok(parsed)
+}
+
+/// reads commands from stdin and runs them on `emulator` until told to quit
+pub fn run(emulator: &mut Emu) {
+    println!("rite-emu debugger, type help for a list of commands");
+    let mut lines = stdin().lock().lines();
+    loop {
+        print!("> ");
+        let _ = stdout().flush();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return, // stdin closed
+        };
+
+        match parse_command(&line) {
+            Ok(Command::Quit) => return,
+            Ok(command) => execute(emulator, command),
+            Err(message) => println!("{}", message),
+        }
+    }
+}
+
+/// carries out everything but `Command::Quit`
+fn execute(emulator: &mut Emu, command: Command) {
+    match command {
+        Command::Step => step(emulator, 1),
+        Command::Run(count) => step(emulator, count),
+        Command::Regs => println!("{}", emulator.status_line()),
+        Command::Mem { address, len } => {
+            let bytes = emulator.memory_range(address, len);
+            for (row, chunk) in (address..).step_by(16).zip(bytes.chunks(16)) {
+                let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+                println!("0x{:03X}  {}", row, hex.join(" "));
+            }
+        }
+        Command::Break(address) => {
+            if emulator.remove_breakpoint(address) {
+                println!("cleared breakpoint at 0x{:03X}", address);
+            } else {
+                emulator.add_breakpoint(address);
+                println!("set breakpoint at 0x{:03X}", address);
+            }
+        }
+        Command::Disasm { address, count } => {
+            let address = address.unwrap_or(emulator.pc());
+            for line in emulator.disassemble(address, count) {
+                println!("{}", line);
+            }
+        }
+        Command::Help => println!("{}", HELP),
+        Command::Quit => (),
+    }
+}
+
+/// runs up to `count` instructions, stopping at errors and breakpoints
+fn step(emulator: &mut Emu, count: usize) {
+    for ran in 0..count {
+        if let Err(e) = emulator.fetch_decode_execute_instr() {
+            println!("emulation error: {}", e);
+            break;
+        }
+        let hits = emulator.take_self_modify_hits();
+        for hit in &hits {
+            println!(
+                "self-modifying code: 0x{:03X} wrote to 0x{:03X}",
+                hit.pc, hit.address
+            );
+        }
+        if !hits.is_empty() && ran + 1 < count {
+            break;
+        }
+        if emulator.at_breakpoint() && ran + 1 < count {
+            println!("hit breakpoint");
+            break;
+        }
+    }
+    println!("{}", emulator.status_line());
+}
+
+#[test]
+fn test_parse_command() {
+    assert_eq!(parse_command("step"), Ok(Command::Step));
+    assert_eq!(parse_command("  run 20 "), Ok(Command::Run(20)));
+    assert_eq!(
+        parse_command("mem 0x200 16"),
+        Ok(Command::Mem {
+            address: 0x200,
+            len: 16
+        })
+    );
+    assert_eq!(parse_command("break 0x2a4"), Ok(Command::Break(0x2a4)));
+    assert_eq!(
+        parse_command("disasm"),
+        Ok(Command::Disasm {
+            address: None,
+            count: 10
+        })
+    );
+    assert_eq!(
+        parse_command("disasm 0x300 4"),
+        Ok(Command::Disasm {
+            address: Some(0x300),
+            count: 4
+        })
+    );
+    assert_eq!(parse_command("q"), Ok(Command::Quit));
+
+    assert!(parse_command("").is_err());
+    assert!(parse_command("run").is_err());
+    assert!(parse_command("run lots").is_err());
+    assert!(parse_command("mem 0x200").is_err());
+    assert!(parse_command("jump 0x200").is_err());
+}
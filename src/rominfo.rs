@@ -0,0 +1,55 @@
+// identifying ROMs by their size and hashes,
+// so people can tell exactly which ROM they're running
+
+/// Identifying information for a ROM file.
+///
+/// # Fields
+/// * `size` - size of the ROM in bytes
+/// * `sha1` - SHA-1 digest of the ROM
+/// * `crc32` - CRC32 checksum of the ROM
+pub struct RomInfo {
+    pub size: usize,
+    pub sha1: [u8; 20],
+    pub crc32: u32,
+}
+
+impl RomInfo {
+    pub fn new(rom: &[u8]) -> Self {
+        RomInfo {
+            size: rom.len(),
+            sha1: sha1_smol::Sha1::from(rom).digest().bytes(),
+            crc32: crc32fast::hash(rom),
+        }
+    }
+
+    /// the SHA-1 digest as lowercase hex, the way most tools print it
+    pub fn sha1_hex(&self) -> String {
+        self.sha1
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for RomInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "size:  {} bytes", self.size)?;
+        writeln!(f, "SHA-1: {}", self.sha1_hex())?;
+        write!(f, "CRC32: {:08x}", self.crc32)
+    }
+}
+
+#[test]
+fn test_rom_hashes() {
+    // known values for the usual check strings
+    let info = RomInfo::new(b"abc");
+    assert_eq!(info.size, 3);
+    assert_eq!(info.sha1_hex(), "a9993e364706816aba3e25717850c26c9cd0d89d");
+
+    let info = RomInfo::new(b"123456789");
+    assert_eq!(info.crc32, 0xcbf43926);
+
+    // longer than one block, to make sure the padding is right
+    let info = RomInfo::new(&[0x61; 100]);
+    assert_eq!(info.sha1_hex(), "7f9000257a4918d7072655ea468540cdcbd42e0c");
+}
@@ -0,0 +1,95 @@
+// smoke testing ROMs without a window.
+// runs a ROM for a while as fast as it'll go and sums up how it went,
+// for checking from a script that a ROM at least doesn't crash
+
+use rite_emu::emu::{Emu, EmulationError};
+
+/// frames in one second of running, the same 60 Hz the window runs at
+const FRAMES_PER_SECOND: u32 = 60;
+
+/// How a smoke run went.
+///
+/// # Fields
+/// * `frames` - frames that ran, including the one an error stopped partway
+/// * `instructions` - instructions run by the frames that finished
+/// * `error` - the emulation error that stopped the run, `None` if it ran the whole time
+/// * `lit_pixels` - pixels lit on the screen at the end
+/// * `framebuffer_hash` - hash of the screen at the end, see `Emu::framebuffer_hash`
+pub struct SmokeReport {
+    pub frames: u32,
+    pub instructions: u64,
+    pub error: Option<EmulationError>,
+    pub lit_pixels: usize,
+    pub framebuffer_hash: u64,
+}
+
+impl SmokeReport {
+    /// runs `seconds` worth of frames of `cycles_per_frame` instructions each,
+    /// without waiting between them, stopping early at an emulation error
+    pub fn run(emulator: &mut Emu, seconds: u32, cycles_per_frame: u32) -> Self {
+        let mut frames = 0;
+        let mut instructions = 0;
+        let mut error = None;
+        for _ in 0..seconds * FRAMES_PER_SECOND {
+            frames += 1;
+            match emulator.step_frame(cycles_per_frame) {
+                Ok(ran) => instructions += ran as u64,
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        SmokeReport {
+            frames,
+            instructions,
+            error,
+            lit_pixels: emulator.lit_pixel_count(),
+            framebuffer_hash: emulator.framebuffer_hash(),
+        }
+    }
+
+    /// whether the ROM ran the whole time without an error
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl std::fmt::Display for SmokeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "frames:       {}", self.frames)?;
+        writeln!(f, "instructions: {}", self.instructions)?;
+        match &self.error {
+            Some(e) => writeln!(f, "error:        {}", e)?,
+            None => writeln!(f, "error:        none")?,
+        }
+        writeln!(f, "lit pixels:   {}", self.lit_pixels)?;
+        write!(f, "screen hash:  {:016x}", self.framebuffer_hash)
+    }
+}
+
+#[test]
+fn test_smoke_report() {
+    // LD I, 0x050 (the 0 in the font); DRW V0, V0, 5; (0x204) JP 0x204
+    let rom = [0xa0, 0x50, 0xd0, 0x05, 0x12, 0x04];
+    let mut emulator = Emu::from_rom(&rom).unwrap();
+    let report = SmokeReport::run(&mut emulator, 2, 10);
+    assert!(report.passed());
+    assert_eq!(report.frames, 120);
+    assert_eq!(report.instructions, 1200);
+    // the 0 has 14 pixels lit
+    assert_eq!(report.lit_pixels, 14);
+    assert_eq!(report.framebuffer_hash, emulator.framebuffer_hash());
+    let text = report.to_string();
+    assert!(text.contains("frames:       120"));
+    assert!(text.contains("error:        none"));
+
+    // running off into 0000 stops it partway through the first frame
+    let mut emulator = Emu::from_rom(&[0x60, 0x01]).unwrap();
+    let report = SmokeReport::run(&mut emulator, 1, 10);
+    assert!(!report.passed());
+    assert_eq!(report.frames, 1);
+    assert_eq!(report.instructions, 0);
+    assert_eq!(report.lit_pixels, 0);
+    assert!(!report.to_string().contains("error:        none"));
+}